@@ -0,0 +1,180 @@
+# 入力ごとの特徴量を出して、python run.py が書いた tools/scores.tsv のスコアとの相関を見る
+# - 地形: 耐久値の平均、標準偏差、隣接マスの差の平均(荒さ)、Moran's I(空間自己相関、格子点の間隔 12 離れたマスどうし)
+# - 配置: 家の広がり、家どうしの最近傍距離(固まり具合)、家から一番近い水源までの距離、端末の最小全域木の長さ
+# - W, K, C
+# スコアは C でほぼ決まるので、同じ C の平均との比(log)との相関も出す
+# tools/features.tsv に特徴量とスコアを書き出す
+import math
+import sys
+
+IN_DIR = 'tools/in'
+SCORES = 'tools/scores.tsv'
+OUTPUT = 'tools/features.tsv'
+WORST = 10
+# 隣接マスどうしだとどのケースもほぼ 1 になるので、サンプリングの格子点の間隔で測る
+MORAN_LAG = 12
+
+
+def read_input(path):
+    tokens = [int(x) for x in open(path).read().split()]
+    n, w, k, c = tokens[:4]
+    pos = 4
+    field = [tokens[pos + y * n:pos + (y + 1) * n] for y in range(n)]
+    pos += n * n
+    points = [(tokens[pos + 2 * i], tokens[pos + 2 * i + 1]) for i in range(w + k)]
+    return n, w, k, c, field, points[:w], points[w:]
+
+
+def manhattan(a, b):
+    return abs(a[0] - b[0]) + abs(a[1] - b[1])
+
+
+# 縦横に lag 離れたマスの組について、差の絶対値の平均と Moran's I
+def lag_pairs(field, lag, mean):
+    n = len(field)
+    diff = 0
+    cross = 0.0
+    pairs = 0
+    for y in range(n):
+        for x in range(n):
+            a = field[y][x]
+            for ny, nx in ((y + lag, x), (y, x + lag)):
+                if ny < n and nx < n:
+                    b = field[ny][nx]
+                    diff += abs(a - b)
+                    cross += (a - mean) * (b - mean)
+                    pairs += 1
+    return diff / pairs, cross / pairs
+
+
+def terrain(field):
+    values = [v for row in field for v in row]
+    mean = sum(values) / len(values)
+    var = sum((v - mean) ** 2 for v in values) / len(values)
+    roughness, _ = lag_pairs(field, 1, mean)
+    _, cross = lag_pairs(field, MORAN_LAG, mean)
+    moran = cross / var if var > 0 else 0.0
+    return mean, math.sqrt(var), roughness, moran
+
+
+# プリム法で端末をマンハッタン距離で結ぶ最小全域木の長さ
+def mst_length(points):
+    dist = [float('inf')] * len(points)
+    used = [False] * len(points)
+    dist[0] = 0
+    total = 0
+    for _ in points:
+        i = min((i for i in range(len(points)) if not used[i]), key=lambda i: dist[i])
+        used[i] = True
+        total += dist[i]
+        for j in range(len(points)):
+            if not used[j]:
+                dist[j] = min(dist[j], manhattan(points[i], points[j]))
+    return total
+
+
+def features(seed):
+    n, w, k, c, field, sources, houses = read_input(f'{IN_DIR}/{seed:04}.txt')
+    mean, std, roughness, moran = terrain(field)
+    cy = sum(y for y, _ in houses) / k
+    cx = sum(x for _, x in houses) / k
+    source_dist = [min(manhattan(h, s) for s in sources) for h in houses]
+    nearest = [min(manhattan(h, g) for g in houses if g is not h) for h in houses] if k >= 2 else [0]
+    return {
+        'W': w,
+        'K': k,
+        'C': c,
+        'mean': mean,
+        'std': std,
+        'roughness': roughness,
+        'moran': moran,
+        'house_spread': sum(abs(y - cy) + abs(x - cx) for y, x in houses) / k,
+        'house_nearest': sum(nearest) / len(nearest),
+        'source_dist_mean': sum(source_dist) / k,
+        'source_dist_max': max(source_dist),
+        'mst': mst_length(sources + houses),
+    }
+
+
+# "seed\tW\tK\tC\tscore" の行 (python run.py が書く)
+def read_scores():
+    scores = {}
+    for line in open(SCORES):
+        if line.startswith('#') or not line.strip():
+            continue
+        seed, _, _, _, score = line.split('\t')
+        scores[int(seed)] = int(score)
+    return scores
+
+
+def ranks(xs):
+    order = sorted(range(len(xs)), key=lambda i: xs[i])
+    r = [0.0] * len(xs)
+    i = 0
+    while i < len(order):
+        j = i
+        while j + 1 < len(order) and xs[order[j + 1]] == xs[order[i]]:
+            j += 1
+        for t in range(i, j + 1):
+            r[order[t]] = (i + j) / 2
+        i = j + 1
+    return r
+
+
+def pearson(xs, ys):
+    mx = sum(xs) / len(xs)
+    my = sum(ys) / len(ys)
+    sxy = sum((x - mx) * (y - my) for x, y in zip(xs, ys))
+    sxx = sum((x - mx) ** 2 for x in xs)
+    syy = sum((y - my) ** 2 for y in ys)
+    if sxx == 0 or syy == 0:
+        return 0.0
+    return sxy / math.sqrt(sxx * syy)
+
+
+def spearman(xs, ys):
+    return pearson(ranks(xs), ranks(ys))
+
+
+def main():
+    scores = read_scores()
+    seeds = sorted(scores)
+    rows = []
+    for i, seed in enumerate(seeds):
+        rows.append((seed, features(seed), scores[seed]))
+        sys.stdout.write("\033[2K\033[G")
+        print(f'{i + 1}/{len(seeds)}', end='', flush=True)
+    print()
+
+    # 同じ C の平均スコアとの比
+    by_c = {}
+    for _, f, score in rows:
+        by_c.setdefault(f['C'], []).append(score)
+    relative = [math.log(score / (sum(by_c[f['C']]) / len(by_c[f['C']]))) for _, f, score in rows]
+
+    names = list(rows[0][1])
+    with open(OUTPUT, 'w') as out:
+        out.write('\t'.join(['seed'] + names + ['score', 'relative']) + '\n')
+        for (seed, f, score), rel in zip(rows, relative):
+            out.write('\t'.join([f'{seed:04}'] + [f'{f[name]:.4g}' for name in names] + [str(score), f'{rel:.4f}']) + '\n')
+
+    print("--------------------------")
+    print('spearman  score  relative (同じ C の平均との比)')
+    corr = []
+    for name in names:
+        xs = [f[name] for _, f, _ in rows]
+        corr.append((name, spearman(xs, [s for _, _, s in rows]), spearman(xs, relative)))
+    for name, raw, rel in sorted(corr, key=lambda t: -abs(t[2])):
+        print(f'{name:17}: {raw:+.3f} {rel:+.3f}')
+    print("--------------------------")
+    print(f'worst {WORST} (relative)')
+    worst = sorted(zip(rows, relative), key=lambda t: -t[1])[:WORST]
+    for (seed, f, score), rel in worst:
+        detail = ', '.join(f'{name}: {f[name]:.4g}' for name in names)
+        print(f'{seed:04}: score: {score}, relative: {rel:+.3f}, {detail}')
+    print("--------------------------")
+    print(f'saved to {OUTPUT}')
+
+
+if __name__ == '__main__':
+    main()
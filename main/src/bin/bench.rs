@@ -0,0 +1,205 @@
+// 重い処理の実行時間を合成ケースで測る
+// cargo run --release --features local --bin bench            計測して、前のコミットの結果と比べる
+// cargo run --release --features local --bin bench -- --save  計測結果を bench/history.tsv に追記する
+// cargo run --release --features local --bin bench -- --calibrate  仮想時計の較正値 (Budget::SEARCH_START, CLAIMS_PER_SEC) を測る
+
+#[path = "../main.rs"]
+#[allow(dead_code)]
+mod solver;
+#[path = "../synthetic.rs"]
+mod synthetic;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use solver::{BinaryQueue, Budget, Field, Grid, Judge, RadixHeap, Smoothing, Strategy, UnionFind};
+use std::time::Instant;
+
+const HISTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/bench/history.tsv");
+// 各計測を何回繰り返すか (中央値と最小値を出す)
+const REPEAT: usize = 10;
+// (seed, W, K, C): sampling 点の数は W + K で変わる
+const CASES: [(u64, usize, usize, usize); 2] = [(1, 1, 1, 1), (2, 4, 10, 128)];
+// 較正は 1 ケース 5 秒かかるので、sampling 点の数と C の違うものを少しだけ
+const CALIBRATION_CASES: [(u64, usize, usize, usize); 5] = [(1, 1, 1, 1), (2, 4, 10, 128), (3, 2, 5, 16), (4, 4, 1, 4), (5, 1, 10, 64)];
+
+struct Record {
+    name: String,
+    median: f64, // ms
+    min: f64,    // ms
+}
+
+fn measure<F: FnMut()>(name: String, mut f: F) -> Record {
+    let mut times = (0..REPEAT)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_secs_f64() * 1000.0
+        })
+        .collect::<Vec<_>>();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    Record { name, median: times[REPEAT / 2], min: times[0] }
+}
+
+// サンプリングと推定まで済ませた Field を作る
+fn prepare(seed: u64, w: usize, k: usize, c: usize) -> Field {
+    let mut judge = synthetic::generate(seed, w, k, c);
+    let input = judge.read_input().unwrap();
+    let mut field = Field::new(input.n, w, k, c, Strategy::select(w, k, c));
+    field.guess_field(&input.sources, &input.houses, &mut judge).unwrap();
+    field.guess_estimate();
+    field
+}
+
+fn bench_case(seed: u64, w: usize, k: usize, c: usize) -> Vec<Record> {
+    let case = format!("W{}K{}C{}", w, k, c);
+    let mut field = prepare(seed, w, k, c);
+    let mut records = vec![];
+
+    let sampling = field.sampling.clone();
+    records.push(measure(format!("dijkstra_vec all binary/{}", case), || {
+        for &s in &sampling {
+            std::hint::black_box(field.dijkstra_vec_with::<BinaryQueue>(s, &sampling));
+        }
+    }));
+    records.push(measure(format!("dijkstra_vec all radix/{}", case), || {
+        for &s in &sampling {
+            std::hint::black_box(field.dijkstra_vec_with::<RadixHeap>(s, &sampling));
+        }
+    }));
+
+    let threads = solver::worker_threads();
+    records.push(measure(format!("all_pairs threads/{}", case), || {
+        std::hint::black_box(field.all_pairs(threads));
+    }));
+
+    // 端末 + ランダムな Steiner 点で木を作る
+    field.build_paths(threads);
+    let m = sampling.len();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let keys_list = (0..100)
+        .map(|_| {
+            let mut keys = (0..w + k).collect::<Vec<_>>();
+            keys.extend((w + k..m).filter(|_| rng.gen_range(0, 8) == 0));
+            keys
+        })
+        .collect::<Vec<_>>();
+    records.push(measure(format!("state_generate x100/{}", case), || {
+        for keys in &keys_list {
+            std::hint::black_box(field.state_generate(keys));
+        }
+    }));
+    records
+}
+
+// guess_estimate と同じ設定で、格子点を固定して 200 * 200 をならす
+fn bench_smoothing() -> Record {
+    let mut rng = StdRng::seed_from_u64(0);
+    let grid = Grid::from_fn(200, |_| rng.gen_range(10, 5001));
+    let pinned = Grid::from_fn(200, |p| p.y % 12 == 8 && p.x % 12 == 8);
+    measure("smoothing r2 x3".to_string(), || {
+        std::hint::black_box(Smoothing { radius: 2, passes: 3 }.apply(&grid, Some(&pinned)));
+    })
+}
+
+// 盤面と同じ 200 * 200 要素で、ランダムに merge と same を繰り返す
+fn bench_union_find() -> Record {
+    const N: usize = 200 * 200;
+    let mut rng = StdRng::seed_from_u64(0);
+    let ops = (0..N).map(|_| (rng.gen_range(0, N), rng.gen_range(0, N))).collect::<Vec<_>>();
+    measure("union_find 40000 merge + same".to_string(), || {
+        let mut uf = UnionFind::new(N);
+        for &(a, b) in &ops {
+            uf.merge(a, b);
+        }
+        for &(a, b) in &ops {
+            std::hint::black_box(uf.same(a, b));
+        }
+    })
+}
+
+fn git(args: &[&str]) -> String {
+    std::process::Command::new("git")
+        .args(args)
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+// 今のコミット、作業ツリーに変更があれば -dirty を付ける
+fn commit() -> String {
+    let hash = git(&["rev-parse", "--short", "HEAD"]);
+    let hash = if hash.is_empty() { "unknown".to_string() } else { hash };
+    if git(&["status", "--porcelain", "--untracked-files=no"]).is_empty() {
+        hash
+    } else {
+        format!("{}-dirty", hash)
+    }
+}
+
+// "commit\tname\tmedian\tmin" の行
+fn read_history() -> Vec<(String, String, f64)> {
+    let text = std::fs::read_to_string(HISTORY).unwrap_or_default();
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let v = line.split('\t').collect::<Vec<_>>();
+            Some((v.first()?.to_string(), v.get(1)?.to_string(), v.get(2)?.parse().ok()?))
+        })
+        .collect()
+}
+
+// 壁時計で solver を最後まで回して、Search の開始時刻と claim の速さの中央値を出す
+// claim の速さはジャッジと同じ1スレッドで測る
+fn calibrate() {
+    std::env::set_var("THREADS", "1");
+    let mut starts = vec![];
+    let mut rates = vec![];
+    for &(seed, w, k, c) in &CALIBRATION_CASES {
+        let mut judge = synthetic::generate(seed, w, k, c);
+        let mut budget = Budget::new();
+        solver::seed_rng(seed);
+        solver::run(&mut judge, &mut budget).unwrap();
+        budget.report();
+        let (start, rate) = budget.search_rate();
+        starts.push(start);
+        rates.push(rate);
+    }
+    starts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    rates.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    println!("SEARCH_START: {:.2}, CLAIMS_PER_SEC: {:.0}", starts[starts.len() / 2], rates[rates.len() / 2]);
+}
+
+fn main() {
+    if std::env::args().any(|a| a == "--calibrate") {
+        calibrate();
+        return;
+    }
+    let save = std::env::args().any(|a| a == "--save");
+    let commit = commit();
+    let mut records = vec![];
+    for &(seed, w, k, c) in &CASES {
+        records.extend(bench_case(seed, w, k, c));
+    }
+    records.push(bench_smoothing());
+    records.push(bench_union_find());
+
+    // 別のコミットで最後に保存された結果と比べる
+    let history = read_history();
+    let base = history.iter().rev().map(|(c, _, _)| c).find(|&c| *c != commit);
+    println!("commit: {}, base: {}", commit, base.map_or("-", |c| c.as_str()));
+    for r in &records {
+        let prev = base.and_then(|b| history.iter().find(|(c, name, _)| c == b && *name == r.name)).map(|&(_, _, m)| m);
+        let diff = prev.map_or("-".to_string(), |p| format!("{:+.1}%", (r.median / p - 1.0) * 100.0));
+        println!("{:40} median: {:9.3} ms, min: {:9.3} ms, diff: {}", r.name, r.median, r.min, diff);
+    }
+
+    if save {
+        let mut text = std::fs::read_to_string(HISTORY).unwrap_or_else(|_| "# commit\tname\tmedian_ms\tmin_ms\n".to_string());
+        for r in &records {
+            text += &format!("{}\t{}\t{:.3}\t{:.3}\n", commit, r.name, r.median, r.min);
+        }
+        std::fs::create_dir_all(concat!(env!("CARGO_MANIFEST_DIR"), "/bench")).unwrap();
+        std::fs::write(HISTORY, text).unwrap();
+        println!("saved to {}", HISTORY);
+    }
+}
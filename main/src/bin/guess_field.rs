@@ -1,5 +1,11 @@
 use proconio::input;
 
+#[path = "../main.rs"]
+#[allow(dead_code)]
+mod solver;
+
+use solver::{Grid, Smoothing};
+
 struct Guess {
     n: usize,
     field: Vec<Vec<i32>>,
@@ -16,14 +22,6 @@ impl Guess {
         }
     }
     
-    fn clone(&self) -> Self {
-        Self {
-            n: self.n,
-            field: self.field.clone(),
-            protected: self.protected.clone(),
-        }
-    }
-
     fn set(&mut self, y: usize, x: usize, v: i32) {
         self.field[y][x] = v;
         self.protected[y][x] = true;
@@ -43,33 +41,17 @@ impl Guess {
                 self.field[y][x] = self.field[ny][nx];
             }
         }
-        for _ in 0..30 {
-            self.flatten();
-        }
+        self.flatten();
     }
 
+    // 4近傍平均30回と同じくらいの広さでならす、set したマスは動かさない
     fn flatten(&mut self) {
-        let mut new_field = self.field.clone();
-        let dxy = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
-        for y in 0..self.n {
-            for x in 0..self.n {
-                let mut sum = 0;
-                let mut cnt = 0;
-                for &(dy, dx) in &dxy {
-                    let ny = y as i32 + dy;
-                    let nx = x as i32 + dx;
-                    if ny < 0 || ny >= self.n as i32 || nx < 0 || nx >= self.n as i32 {
-                        continue;
-                    }
-                    let ny = ny as usize;
-                    let nx = nx as usize;
-                    sum += self.field[ny][nx];
-                    cnt += 1;
-                }
-                new_field[y][x] = sum / cnt as i32;
-            }
+        let field = Grid::from_fn(self.n, |p| self.field[p.y][p.x]);
+        let protected = Grid::from_fn(self.n, |p| self.protected[p.y][p.x]);
+        let field = Smoothing::gaussian(3.87, 3).apply(&field, Some(&protected));
+        for (row, smoothed) in self.field.iter_mut().zip(field.rows()) {
+            row.copy_from_slice(smoothed);
         }
-        self.field = new_field;
     }
 
 }
@@ -0,0 +1,91 @@
+// 入力ケースとソルバーの出力(クエリ列)を受け取って、出力が正しいか確かめる
+// cargo run --release --bin validate -- tools/in/0000.txt tools/out/0000.txt
+
+#[path = "../main.rs"]
+#[allow(dead_code)]
+mod solver;
+
+use solver::{Judge, LocalJudge, Pos};
+
+#[derive(Default)]
+struct Report {
+    queries: usize,
+    malformed: usize,       // "y x P" として読めない行
+    out_of_range: usize,    // 座標が範囲外
+    invalid_power: usize,   // P が 1..=5000 でない
+    broken_hits: usize,     // 破壊済マスを叩いた
+    after_completion: usize, // 全部つながったあとのクエリ
+}
+
+impl Report {
+    fn errors(&self) -> usize {
+        self.malformed + self.out_of_range + self.invalid_power + self.broken_hits + self.after_completion
+    }
+}
+
+fn validate(judge: &mut LocalJudge, output: &str) -> Report {
+    let mut report = Report::default();
+    let n = judge.input.n;
+    for line in output.lines() {
+        let line = line.trim();
+        // ソルバーのコメント行
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        report.queries += 1;
+        let v = line.split_whitespace().map(|t| t.parse::<i64>()).collect::<Result<Vec<_>, _>>();
+        let (y, x, power) = match v.as_deref() {
+            Ok(&[y, x, power]) => (y, x, power),
+            _ => {
+                report.malformed += 1;
+                continue;
+            }
+        };
+        if y < 0 || y >= n as i64 || x < 0 || x >= n as i64 {
+            report.out_of_range += 1;
+            continue;
+        }
+        let p = Pos::new(y as usize, x as usize);
+        if !(1..=5000).contains(&power) {
+            report.invalid_power += 1;
+            continue;
+        }
+        if judge.completed {
+            report.after_completion += 1;
+            continue;
+        }
+        if judge.is_broken[p] {
+            report.broken_hits += 1;
+            continue;
+        }
+        if let Err(e) = judge.query(p, power as i32) {
+            unreachable!("{}", e);
+        }
+    }
+    report
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() != 3 {
+        eprintln!("usage: {} <input> <output>", args[0]);
+        std::process::exit(2);
+    }
+    let input = std::fs::File::open(&args[1]).expect("cannot open input");
+    let mut judge = LocalJudge::from_reader(std::io::BufReader::new(input)).expect("invalid input");
+    let output = std::fs::read_to_string(&args[2]).expect("cannot read output");
+
+    let report = validate(&mut judge, &output);
+    let connected = judge.is_connected();
+    println!("queries: {}", report.queries);
+    println!("malformed: {}", report.malformed);
+    println!("out_of_range: {}", report.out_of_range);
+    println!("invalid_power: {}", report.invalid_power);
+    println!("broken_hits: {}", report.broken_hits);
+    println!("after_completion: {}", report.after_completion);
+    println!("connected: {}", connected);
+    println!("cost: {}", judge.total_cost);
+    if report.errors() > 0 || !connected {
+        std::process::exit(1);
+    }
+}
@@ -1,60 +1,331 @@
-use std::{io::{BufReader, BufRead}, collections::BTreeSet, string};
+use std::io::{BufRead, Write};
 
-use proconio::{source::line::LineSource, input};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{FromEntropy, Rng, SeedableRng};
+
+thread_local! {
+    // スレッドごとの乱数生成器、seed_rng で固定できる
+    static RNG: std::cell::RefCell<StdRng> = std::cell::RefCell::new(StdRng::from_entropy());
+}
+
+// 今のスレッドの乱数を seed で初期化する (回帰テストなどで再現させたいとき)
+pub(crate) fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
 
 fn rand(l: usize, r: usize) -> usize {
     // [l, r) で乱数生成
-    rand::thread_rng().gen_range(l, r)
+    RNG.with(|rng| rng.borrow_mut().gen_range(l, r))
+}
+
+fn rand_f64() -> f64 {
+    // [0, 1) で乱数生成
+    RNG.with(|rng| rng.borrow_mut().gen::<f64>())
+}
+
+fn rand_u64() -> u64 {
+    RNG.with(|rng| rng.borrow_mut().gen::<u64>())
+}
+
+// 今のスレッドの乱数と rng を入れ替える
+fn swap_rng(rng: &mut StdRng) {
+    RNG.with(|r| std::mem::swap(&mut *r.borrow_mut(), rng));
+}
+
+// 診断ログのレベル、下ほど細かい
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug, // 盤面のダンプなど、提出では出さない
+}
+
+impl Level {
+    const ALL: [Level; 5] = [Level::Off, Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Level> {
+        Self::ALL.iter().copied().find(|l| l.name() == name)
+    }
+}
+
+// ログの出力先
+enum Sink {
+    Comment, // 標準出力に "# " を付けて (ジャッジはコメントとして読み飛ばす)
+    Stderr,
+    File(std::fs::File),
+}
+
+// レベルと対象(target)ごとに出し分ける診断ログ
+// 手元では LOG=info,search=debug LOG_SINK=comment|stderr|file:<path> LOG_FORMAT=text|json で変えられる
+pub(crate) struct Logger {
+    level: Level,
+    targets: Vec<(String, Level)>, // target ごとの上書き
+    sink: Sink,
+    json: bool,          // JSON lines で出す
+    stdout_closed: bool, // ジャッジが終わって標準出力を読まなくなった
+}
+
+static LOGGER: std::sync::Mutex<Logger> = std::sync::Mutex::new(Logger { level: Level::Info, targets: Vec::new(), sink: Sink::Comment, json: false, stdout_closed: false });
+
+impl Logger {
+    // "info,search=debug": target のないものが全体のレベル
+    pub(crate) fn configure(spec: &str, sink: &str, format: &str) -> Result<(), String> {
+        let mut level = Level::Info;
+        let mut targets = vec![];
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            let (target, name) = item.split_once('=').map_or((None, item), |(t, n)| (Some(t), n));
+            let l = Level::parse(name).ok_or_else(|| format!("unknown level: {}", name))?;
+            match target {
+                Some(t) => targets.push((t.to_string(), l)),
+                None => level = l,
+            }
+        }
+        let sink = match sink {
+            "" | "comment" => Sink::Comment,
+            "stderr" => Sink::Stderr,
+            _ => match sink.strip_prefix("file:") {
+                Some(path) => Sink::File(std::fs::File::create(path).map_err(|e| format!("cannot create {}: {}", path, e))?),
+                None => return Err(format!("unknown sink: {}", sink)),
+            },
+        };
+        let json = match format {
+            "" | "text" => false,
+            "json" => true,
+            _ => return Err(format!("unknown format: {}", format)),
+        };
+        *LOGGER.lock().unwrap() = Logger { level, targets, sink, json, stdout_closed: false };
+        Ok(())
+    }
+
+    // ジャッジが Completed を返したら呼ぶ、それ以降のコメントと記録は標準エラーに出す
+    // (ジャッジが先に閉じていると書き込みが broken pipe になる)
+    pub(crate) fn close_stdout() {
+        LOGGER.lock().unwrap().stdout_closed = true;
+    }
+
+    fn write_comment(&self, line: &str) {
+        if self.stdout_closed {
+            eprintln!("{}", line);
+        } else {
+            let _ = writeln!(std::io::stdout().lock(), "{}", line);
+        }
+    }
+
+    fn enabled(&self, level: Level, target: &str) -> bool {
+        let max = self.targets.iter().rev().find(|(t, _)| t == target).map_or(self.level, |&(_, l)| l);
+        level != Level::Off && level <= max
+    }
+
+    fn format(&self, level: Level, target: &str, message: &str) -> String {
+        if self.json {
+            let fields = vec![("level", Json::String(level.name().to_string())), ("target", Json::String(target.to_string())), ("message", Json::String(message.to_string()))];
+            return Json::object(fields).to_string();
+        }
+        let line = format!("[{}] {}: {}", level.name(), target, message);
+        match self.sink {
+            // 複数行でもジャッジがコメントとして読めるように全部の行に付ける
+            Sink::Comment => line.lines().map(|l| format!("# {}", l)).collect::<Vec<_>>().join("\n"),
+            _ => line,
+        }
+    }
+
+    fn write(&mut self, level: Level, target: &str, message: &str) {
+        let line = self.format(level, target, message);
+        let line = match (&self.sink, self.json) {
+            (Sink::Comment, true) => format!("# {}", line),
+            _ => line,
+        };
+        match &mut self.sink {
+            Sink::Comment => self.write_comment(&line),
+            Sink::Stderr => eprintln!("{}", line),
+            Sink::File(file) => {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
 }
 
-struct Timer {
+// log(Level::Info, "search", format_args!(...)): 出さないレベルなら文字列を作らない
+pub(crate) fn log(level: Level, target: &str, args: std::fmt::Arguments) {
+    let mut logger = LOGGER.lock().unwrap();
+    if logger.enabled(level, target) {
+        logger.write(level, target, &args.to_string());
+    }
+}
+
+// 機械で読む記録、レベルや出力先によらず標準出力に "# record: {JSON}" の1行で出す (run.py が読む)
+// ジャッジが終わった後なら標準エラーに出す
+pub(crate) fn record(kind: &str, mut fields: Vec<(&str, Json)>) {
+    fields.insert(0, ("kind", Json::String(kind.to_string())));
+    LOGGER.lock().unwrap().write_comment(&format!("# record: {}", Json::object(fields)));
+}
+
+// 処理のフェーズ
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    Sampling,   // 水源、家、格子点を叩く
+    Estimation, // 耐久値の推定
+    AllPairs,   // sampling 間の最短路
+    Search,     // 焼きなまし
+    Excavation, // 掘削
+}
+
+impl Phase {
+    const ALL: [Phase; 5] = [Phase::Sampling, Phase::Estimation, Phase::AllPairs, Phase::Search, Phase::Excavation];
+
+    fn id(self) -> usize {
+        Self::ALL.iter().position(|&p| p == self).unwrap()
+    }
+}
+
+// Search をどこで打ち切るか
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Clock {
+    Wall,                      // 締め切りの時刻まで
+    Virtual { claims: usize }, // claim をこの回数したら (機械の速さによらず再現できる)
+}
+
+// 制限時間を各フェーズに割り振って管理する
+pub(crate) struct Budget {
     start: std::time::Instant,
+    deadline: [f32; 5], // 各フェーズが終わっているべき時刻(開始からの秒数)
+    phase: Phase,
+    phase_start: f32,
+    used: [f32; 5],
+    clock: Clock,
+    claims: usize, // Search で claim した回数
 }
 
-impl Timer {
-    fn new() -> Self {
+impl Budget {
+    const TIME_LIMIT: f32 = 5.0;
+    // 掘削のために残しておく時間
+    const EXCAVATION_RESERVE: f32 = 0.5;
+    // Search 以外は目安、超えたら報告する
+    const DEADLINE: [f32; 5] = [2.5, 2.7, 3.3, Self::TIME_LIMIT - Self::EXCAVATION_RESERVE, Self::TIME_LIMIT];
+    // 仮想時計の較正値: 手元で壁時計のまま回したときの Search の開始時刻と claim の速さの中央値
+    // claim の速さは状態の大きさでケースごとに数倍違うので、仮想時計の Search の長さは目安
+    // 測り直すときは cargo run --release --features local --bin bench -- --calibrate
+    const SEARCH_START: f32 = 1.0;
+    const CLAIMS_PER_SEC: f32 = 18000.0;
+
+    pub(crate) fn new() -> Self {
+        Self::with_limit(Self::TIME_LIMIT)
+    }
+
+    // 制限時間を limit 秒に縮めたもの、各フェーズの締め切りも同じ比率で縮める
+    pub(crate) fn with_limit(limit: f32) -> Self {
         Self {
             start: std::time::Instant::now(),
+            deadline: Self::DEADLINE.map(|d| d * limit / Self::TIME_LIMIT),
+            phase: Phase::Sampling,
+            phase_start: 0.0,
+            used: [0.0; 5],
+            clock: Clock::Wall,
+            claims: 0,
         }
     }
 
-    fn is_timeout(&self, limit: f32) -> bool {
-        let elapsed = self.start.elapsed().as_secs_f32();
-        elapsed < limit
+    // Search を claim の回数で打ち切る仮想時計、他のフェーズは limit 秒の締め切りを目安にする
+    pub(crate) fn with_claims(limit: f32, claims: usize) -> Self {
+        Self { clock: Clock::Virtual { claims }, ..Self::with_limit(limit) }
+    }
+
+    // 手元で測った claim の速さから、limit 秒の Search に相当する回数を出す
+    pub(crate) fn calibrated_claims(limit: f32) -> usize {
+        let search = (Self::DEADLINE[Phase::Search.id()] - Self::SEARCH_START) * limit / Self::TIME_LIMIT;
+        (search * Self::CLAIMS_PER_SEC) as usize
+    }
+
+    fn elapsed(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+
+    // 今のフェーズを終えて次のフェーズに入る
+    fn begin(&mut self, phase: Phase) {
+        let now = self.elapsed();
+        self.used[self.phase.id()] += now - self.phase_start;
+        self.phase = phase;
+        self.phase_start = now;
+    }
+
+    // 今のフェーズの締め切りを過ぎたか
+    fn is_over(&self) -> bool {
+        match self.clock {
+            Clock::Virtual { claims } if self.phase == Phase::Search => self.claims >= claims,
+            _ => self.elapsed() >= self.deadline[self.phase.id()],
+        }
+    }
+
+    // claim した回数を数える (仮想時計はこれで進む)
+    fn tick(&mut self, claims: usize) {
+        self.claims += claims;
     }
 
     fn now_time(&self, message: String) {
-        let elapsed = self.start.elapsed().as_secs_f32();
-        println!("# time: {}, message: {}", elapsed, message);
+        log(Level::Info, "budget", format_args!("time: {}, phase: {:?}, message: {}", self.elapsed(), self.phase, message));
+    }
+
+    // 各フェーズで実際に使った時間を出す
+    pub(crate) fn report(&mut self) {
+        self.begin(self.phase);
+        for &phase in &Phase::ALL {
+            let over = if self.used[..=phase.id()].iter().sum::<f32>() > self.deadline[phase.id()] { " (over)" } else { "" };
+            log(Level::Info, "budget", format_args!("phase: {:?}, used: {:.3}, deadline: {:.3}{}", phase, self.used[phase.id()], self.deadline[phase.id()], over));
+        }
+        let (search_start, claims_per_sec) = self.search_rate();
+        let clock = match self.clock {
+            Clock::Wall => "wall",
+            Clock::Virtual { .. } => "virtual",
+        };
+        log(Level::Info, "budget", format_args!("clock: {}, claims: {}, search_start: {:.3}, claims_per_sec: {:.0}", clock, self.claims, search_start, claims_per_sec));
+    }
+
+    // Search を始めた時刻と claim の速さ (仮想時計の較正に使う)
+    pub(crate) fn search_rate(&self) -> (f32, f32) {
+        let search_start = self.used[..Phase::Search.id()].iter().sum::<f32>();
+        let search = self.used[Phase::Search.id()];
+        (search_start, self.claims as f32 / search.max(1e-6))
     }
 }
 
-struct UnionFind {
-    n: usize,
+pub(crate) struct UnionFind {
     par: Vec<i32>,
 }
 
 impl UnionFind {
-    fn new(n: usize) -> Self {
+    pub(crate) fn new(n: usize) -> Self {
         Self {
-            n,
             par: vec![-1; n],
         }
     } 
 
-    fn merge(&mut self, a: usize, b: usize) -> usize {
+    pub(crate) fn merge(&mut self, a: usize, b: usize) -> usize {
         let mut x = self.leader(a);
         let mut y = self.leader(b);
+        if x == y {
+            return x;
+        }
         if -self.par[x] < -self.par[y] {
             std::mem::swap(&mut x, &mut y);
         }
         self.par[x] += self.par[y];
         self.par[y] = x as i32;
-        return x;
+        x
     }
 
-    fn leader(&mut self, a: usize) -> usize {
+    pub(crate) fn leader(&mut self, a: usize) -> usize {
         if self.par[a] < 0 {
             a 
         } else {
@@ -63,630 +334,3001 @@ impl UnionFind {
         }
     }
 
-    fn same(&mut self, a: usize, b: usize) -> bool {
+    pub(crate) fn same(&mut self, a: usize, b: usize) -> bool {
         self.leader(a) == self.leader(b)
     }
 }
 
-enum Responce {
+// 盤面上のマス
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub(crate) struct Pos {
+    pub(crate) y: usize,
+    pub(crate) x: usize,
+}
+
+impl Pos {
+    pub(crate) fn new(y: usize, x: usize) -> Self {
+        Self { y, x }
+    }
+
+    pub(crate) fn manhattan(self, other: Pos) -> usize {
+        self.y.abs_diff(other.y) + self.x.abs_diff(other.x)
+    }
+
+    // DYX[dir] の方向に1マス進む、n×n の盤面から出るなら None
+    pub(crate) fn step(self, dir: u8, n: usize) -> Option<Pos> {
+        let (dy, dx) = DYX[dir as usize];
+        self.moved(dy, dx, n)
+    }
+
+    // DYX[dir] の逆向きに1マス戻る、最短路木で親に向かうときに使う
+    pub(crate) fn back(self, dir: u8, n: usize) -> Option<Pos> {
+        let (dy, dx) = DYX[dir as usize];
+        self.moved(-dy, -dx, n)
+    }
+
+    fn moved(self, dy: i32, dx: i32, n: usize) -> Option<Pos> {
+        let y = self.y.checked_add_signed(dy as isize)?;
+        let x = self.x.checked_add_signed(dx as isize)?;
+        if y < n && x < n {
+            Some(Pos::new(y, x))
+        } else {
+            None
+        }
+    }
+
+    // 盤面内の隣接マスを、そこへの方向 (DYX の index) と一緒に返す
+    pub(crate) fn neighbors(self, n: usize) -> impl Iterator<Item = (u8, Pos)> {
+        (0..DYX.len() as u8).filter_map(move |dir| self.step(dir, n).map(|p| (dir, p)))
+    }
+}
+
+impl std::fmt::Display for Pos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.y, self.x)
+    }
+}
+
+// n×n の盤面の各マスに値を持つ、行優先で1本の Vec に並べる
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Grid<T> {
+    n: usize,
+    data: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub(crate) fn new(n: usize, v: T) -> Self {
+        Self { n, data: vec![v; n * n] }
+    }
+
+    #[cfg(any(test, feature = "local"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn fill(&mut self, v: T) {
+        self.data.iter_mut().for_each(|x| *x = v.clone());
+    }
+}
+
+impl<T> Grid<T> {
+    pub(crate) fn from_fn<F: FnMut(Pos) -> T>(n: usize, mut f: F) -> Self {
+        Self { n, data: (0..n * n).map(|i| f(Pos::new(i / n, i % n))).collect() }
+    }
+
+    // 行優先に並んだ n * n 個の値から作る
+    pub(crate) fn from_vec(n: usize, data: Vec<T>) -> Self {
+        assert_eq!(data.len(), n * n);
+        Self { n, data }
+    }
+
+    pub(crate) fn n(&self) -> usize {
+        self.n
+    }
+
+    pub(crate) fn flat_index(&self, p: Pos) -> usize {
+        p.y * self.n + p.x
+    }
+
+    pub(crate) fn pos(&self, i: usize) -> Pos {
+        Pos::new(i / self.n, i % self.n)
+    }
+
+    // 全マスを行優先で
+    pub(crate) fn positions(&self) -> impl Iterator<Item = Pos> {
+        let n = self.n;
+        (0..n * n).map(move |i| Pos::new(i / n, i % n))
+    }
+
+    pub(crate) fn neighbors(&self, p: Pos) -> impl Iterator<Item = (u8, Pos)> {
+        p.neighbors(self.n)
+    }
+
+    pub(crate) fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.data.chunks(self.n)
+    }
+
+    pub(crate) fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid { n: self.n, data: self.data.iter().map(f).collect() }
+    }
+}
+
+impl<T> std::ops::Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Pos) -> &T {
+        &self.data[p.y * self.n + p.x]
+    }
+}
+
+impl<T> std::ops::IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, p: Pos) -> &mut T {
+        &mut self.data[p.y * self.n + p.x]
+    }
+}
+
+// Grid::index で求めた行優先の index でも引ける
+impl<T> std::ops::Index<usize> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        &self.data[i]
+    }
+}
+
+impl<T> std::ops::IndexMut<usize> for Grid<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        &mut self.data[i]
+    }
+}
+
+// 盤面の値をならす
+// 幅 2 * radius + 1 の箱型平均を summed-area table で1回 O(n^2) で取り、passes 回重ねてガウシアンに近づける
+// 盤面の端では窓を盤面の内側に切り詰めて平均する
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Smoothing {
+    pub(crate) radius: usize,
+    pub(crate) passes: usize,
+}
+
+impl Smoothing {
+    // 標準偏差 sigma のガウシアンに近い、passes 回の箱型平均
+    pub(crate) fn gaussian(sigma: f64, passes: usize) -> Self {
+        // 幅 w の箱型平均の分散は (w^2 - 1) / 12 で、重ねると足し算になる
+        let w = (12.0 * sigma * sigma / passes as f64 + 1.0).sqrt();
+        Self {
+            radius: ((w - 1.0) / 2.0).round() as usize,
+            passes,
+        }
+    }
+
+    // pinned のマスは元の値のまま動かさない、途中は f64 で持って最後に四捨五入する
+    pub(crate) fn apply(&self, grid: &Grid<i32>, pinned: Option<&Grid<bool>>) -> Grid<i32> {
+        let n = grid.n();
+        let r = self.radius;
+        let mut cur = grid.map(|&v| v as f64);
+        // sum[y * (n + 1) + x] は [0, y) × [0, x) の和
+        let mut sum = vec![0.0; (n + 1) * (n + 1)];
+        for _ in 0..self.passes {
+            for (y, row) in cur.rows().enumerate() {
+                let mut acc = 0.0;
+                for (x, &v) in row.iter().enumerate() {
+                    acc += v;
+                    sum[(y + 1) * (n + 1) + x + 1] = sum[y * (n + 1) + x + 1] + acc;
+                }
+            }
+            cur = Grid::from_fn(n, |p| {
+                if pinned.is_some_and(|g| g[p]) {
+                    return grid[p] as f64;
+                }
+                let (y0, y1) = (p.y.saturating_sub(r), std::cmp::min(n, p.y + r + 1));
+                let (x0, x1) = (p.x.saturating_sub(r), std::cmp::min(n, p.x + r + 1));
+                let total = sum[y1 * (n + 1) + x1] - sum[y0 * (n + 1) + x1] - sum[y1 * (n + 1) + x0] + sum[y0 * (n + 1) + x0];
+                total / ((y1 - y0) * (x1 - x0)) as f64
+            });
+        }
+        cur.map(|&v| v.round() as i32)
+    }
+}
+
+type Path = Vec<Pos>;
+
+const DYX: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+// 最短路木で親がいない(始点 or 未到達)マス
+const NO_PARENT: u8 = u8::MAX;
+
+// 最短路木を t から始点に向かって辿る
+struct PathIter<'a> {
+    par: &'a Grid<u8>,
+    pos: Option<Pos>,
+}
+
+impl Iterator for PathIter<'_> {
+    type Item = Pos;
+
+    fn next(&mut self) -> Option<Pos> {
+        let p = self.pos?;
+        let d = self.par[p];
+        self.pos = if d == NO_PARENT { None } else { p.back(d, self.par.n()) };
+        Some(p)
+    }
+}
+
+pub(crate) enum Responce {
     NotBroken,
     Broken,
+    Completed, // 壊れて、全ての家が水源とつながった
+}
+
+#[derive(Debug)]
+pub(crate) enum SolverError {
+    Input(String),          // 入力が読めない、EOFなど
+    InvalidResponce(i64),   // ジャッジの応答が 0, 1, 2 以外
+    InvalidState,           // 出力しようとした状態で家と水源がつながっていない
+    Disconnected,           // 掘り終わったのにつながらなかった
+    NoOracle,               // 本当の耐久値を使うモードなのに、ジャッジが耐久値を知らない
+    InvalidPlan(String),    // 読み込んだ plan の JSON が読めない
 }
 
-fn convert_index(y: usize, dy: i32, x: usize, dx: i32, n: usize) -> Option<(usize, usize)> {
-    let ny = y as i32 + dy;
-    let nx = x as i32 + dx;
-    if ny < 0 || ny >= n as i32 || nx < 0 || nx >= n as i32 {
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverError::Input(message) => write!(f, "invalid input: {}", message),
+            SolverError::InvalidResponce(res) => write!(f, "invalid responce: {}", res),
+            SolverError::InvalidState => write!(f, "invalid state"),
+            SolverError::Disconnected => write!(f, "houses are not connected after excavation"),
+            SolverError::NoOracle => write!(f, "oracle mode needs a judge that knows the durability"),
+            SolverError::InvalidPlan(message) => write!(f, "invalid plan: {}", message),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Input {
+    pub(crate) n: usize,
+    pub(crate) w: usize,
+    pub(crate) k: usize,
+    pub(crate) c: usize,
+    pub(crate) sources: Vec<Pos>,
+    pub(crate) houses: Vec<Pos>,
+}
+
+// ジャッジとのやりとり
+pub(crate) trait Judge {
+    fn read_input(&mut self) -> Result<Input, SolverError>;
+    // p を power で叩く、全ての家がつながったら Completed
+    fn query(&mut self, p: Pos, power: i32) -> Result<Responce, SolverError>;
+    // 本当の耐久値、手元で動かすときだけ分かる
+    fn durability(&self, _p: Pos) -> Option<i32> {
         None
-    } else {
-        Some((ny as usize, nx as usize))
     }
 }
 
-struct Field {
-    n: usize,
-    w: usize,
-    k: usize,
-    c: usize,
-    guess: Vec<Vec<i32>>,
-    is_broken: Vec<Vec<bool>>,
-    real: Vec<Vec<i32>>,
-    total_cost: usize,
-    sampling: Vec<(usize, usize)>, // 水源、家 + 一定間隔で取得したpos
-    dist_path: Vec<Vec<(i32, Vec<(usize, usize)>)>>,
-    houses_idx: Vec<usize>,
-    sources_idx: Vec<usize>,
+// 空白区切りのトークンを読む
+pub(crate) struct Scanner<R: BufRead> {
+    reader: R,
+    tokens: Vec<String>, // 読んだ行の残り、逆順
 }
 
-impl Field {
-    fn new(n: usize, w: usize, k: usize, c: usize) -> Self {
+impl<R: BufRead> Scanner<R> {
+    pub(crate) fn new(reader: R) -> Self {
         Self {
-            n, w, k, c, guess: vec![vec![0; n]; n], is_broken: vec![vec![false; n]; n], real: vec![vec![0; n]; n], total_cost: 0, sampling: vec![], dist_path: vec![],
-            houses_idx: vec![], sources_idx: vec![],
+            reader,
+            tokens: vec![],
         }
     }
 
-    // init
-    fn guess_field<R: BufRead>(&mut self, sources: &Vec<(usize, usize)>, houses: &Vec<(usize, usize)>, line_source: &mut LineSource<R>) {
-        let mut checks = vec![];
-        for &(y, x) in sources {
-            self.guess[y][x] = self.destruct(y, x, true, houses, line_source);
-            checks.push((y, x));
-            self.sources_idx.push(self.sampling.len());
-            self.sampling.push((y, x));
-        } 
-        for &(y, x) in houses {
-            self.guess[y][x] = self.destruct(y, x, true, houses, line_source);
-            checks.push((y, x));
-            self.houses_idx.push(self.sampling.len());
-            self.sampling.push((y, x));
+    pub(crate) fn read<T: std::str::FromStr>(&mut self) -> Result<T, SolverError> {
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return Err(SolverError::Input("unexpected EOF".to_string())),
+                Ok(_) => self.tokens = line.split_whitespace().rev().map(|s| s.to_string()).collect(),
+                Err(e) => return Err(SolverError::Input(e.to_string())),
+            }
         }
+        let token = self.tokens.pop().unwrap();
+        token.parse().map_err(|_| SolverError::Input(format!("cannot parse {:?}", token)))
+    }
 
-        // let step = (8..self.n).step_by(12).collect::<Vec<_>>();
-        // let step = 
-        //     if self.k >= 9 {
-        //         (7..self.n).step_by(12).collect::<Vec<_>>()
-        //     // } else if self.w <= 1 {
-        //         // (12..self.n).step_by(25).collect::<Vec<_>>()
-        //     } else {
-        //         (10..self.n).step_by(20).collect::<Vec<_>>()
-        //     };
+    pub(crate) fn read_pos(&mut self) -> Result<Pos, SolverError> {
+        Ok(Pos::new(self.read()?, self.read()?))
+    }
+}
 
-        let arrowed_min_dist = 5;
-        let rejected_min_dist = 75;
+// 標準入出力でやりとりするジャッジ
+struct IoJudge<R: BufRead, W: Write> {
+    scanner: Scanner<R>,
+    writer: W,
+}
 
-        // let step = (10..self.n).step_by(20).collect::<Vec<_>>();
-        let step = (8..self.n).step_by(12).collect::<Vec<_>>();
-        // let step = (7..self.n).step_by(11).collect::<Vec<_>>();
-        let mut steps = vec![];
-        let mut f1 = true;
-        for &y in &step {
-            f1 ^= true;
-            let mut f2 = true;
-            for &x in &step {
-                f2 ^= true;
-                self.sampling.push((y, x));
-                if f1 ^ f2 {
-                    continue;
+impl<R: BufRead, W: Write> IoJudge<R, W> {
+    fn new(reader: R, writer: W) -> Self {
+        Self {
+            scanner: Scanner::new(reader),
+            writer,
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Judge for IoJudge<R, W> {
+    fn read_input(&mut self) -> Result<Input, SolverError> {
+        let sc = &mut self.scanner;
+        let (n, w, k, c) = (sc.read()?, sc.read()?, sc.read()?, sc.read()?);
+        let sources = (0..w).map(|_| sc.read_pos()).collect::<Result<_, _>>()?;
+        let houses = (0..k).map(|_| sc.read_pos()).collect::<Result<_, _>>()?;
+        Ok(Input { n, w, k, c, sources, houses })
+    }
+
+    fn query(&mut self, p: Pos, power: i32) -> Result<Responce, SolverError> {
+        writeln!(self.writer, "{} {}", p, power).and_then(|_| self.writer.flush()).map_err(|e| SolverError::Input(e.to_string()))?;
+        match self.scanner.read::<i64>()? {
+            0 => Ok(Responce::NotBroken),
+            1 => Ok(Responce::Broken),
+            2 => {
+                Logger::close_stdout();
+                Ok(Responce::Completed)
+            }
+            res => Err(SolverError::InvalidResponce(res)),
+        }
+    }
+}
+
+// 本当の耐久値を知っている手元用のジャッジ、tools/in の形式 (N W K C, 耐久値, 水源, 家) を読む
+pub(crate) struct LocalJudge {
+    pub(crate) input: Input,
+    pub(crate) durability: Grid<i32>,
+    pub(crate) damage: Grid<i32>, // 累積power
+    pub(crate) is_broken: Grid<bool>,
+    pub(crate) total_cost: usize,
+    pub(crate) completed: bool,
+    uf: UnionFind, // 破壊済マスの連結成分
+}
+
+impl LocalJudge {
+    pub(crate) fn new(input: Input, durability: Grid<i32>) -> Self {
+        let n = input.n;
+        Self {
+            input,
+            durability,
+            damage: Grid::new(n, 0),
+            is_broken: Grid::new(n, false),
+            total_cost: 0,
+            completed: false,
+            uf: UnionFind::new(n * n),
+        }
+    }
+
+    pub(crate) fn from_reader<R: BufRead>(reader: R) -> Result<Self, SolverError> {
+        let mut sc = Scanner::new(reader);
+        let (n, w, k, c) = (sc.read()?, sc.read()?, sc.read()?, sc.read()?);
+        let durability = Grid::from_vec(n, (0..n * n).map(|_| sc.read()).collect::<Result<_, _>>()?);
+        let sources = (0..w).map(|_| sc.read_pos()).collect::<Result<_, _>>()?;
+        let houses = (0..k).map(|_| sc.read_pos()).collect::<Result<_, _>>()?;
+        Ok(Self::new(Input { n, w, k, c, sources, houses }, durability))
+    }
+
+    // 全ての家が、破壊済マスを通って水源とつながっているか
+    pub(crate) fn is_connected(&mut self) -> bool {
+        let (sources, houses) = (&self.input.sources, &self.input.houses);
+        let (is_broken, uf) = (&self.is_broken, &mut self.uf);
+        houses.iter().all(|&h| {
+            is_broken[h] && sources.iter().any(|&s| is_broken[s] && uf.same(is_broken.flat_index(h), is_broken.flat_index(s)))
+        })
+    }
+
+    // ジャッジがエラーにするクエリか
+    pub(crate) fn is_valid_query(&self, p: Pos, power: i32) -> bool {
+        p.y < self.input.n && p.x < self.input.n && (1..=5000).contains(&power) && !self.is_broken[p] && !self.completed
+    }
+}
+
+impl Judge for LocalJudge {
+    fn read_input(&mut self) -> Result<Input, SolverError> {
+        Ok(self.input.clone())
+    }
+
+    fn query(&mut self, p: Pos, power: i32) -> Result<Responce, SolverError> {
+        if !self.is_valid_query(p, power) {
+            return Err(SolverError::InvalidResponce(-1));
+        }
+        self.total_cost += self.input.c + power as usize;
+        self.damage[p] += power;
+        if self.damage[p] < self.durability[p] {
+            return Ok(Responce::NotBroken);
+        }
+        self.is_broken[p] = true;
+        for (_, q) in self.is_broken.neighbors(p) {
+            if self.is_broken[q] {
+                self.uf.merge(self.is_broken.flat_index(p), self.is_broken.flat_index(q));
+            }
+        }
+        if self.is_connected() {
+            self.completed = true;
+            return Ok(Responce::Completed);
+        }
+        Ok(Responce::Broken)
+    }
+
+    fn durability(&self, p: Pos) -> Option<i32> {
+        Some(self.durability[p])
+    }
+}
+
+// 1マスを壊すのにかかるコストのモデル
+// destruct と同じ叩き方(schedule)を使うので、経路の見積もりと実際の掘削でコストが一致する
+struct CostModel {
+    c: i32,
+    backoff: Schedule,
+    risk_lambda: f64, // PathCost::Linear で、残りの耐久値 + C に risk_lambda * sigma を足す
+    sampling_schedule: Vec<i32>, // サンプリングで叩く累積power
+    schedule: Vec<i32>,          // 経路を掘るときに叩く累積power、sampling_schedule の間を埋めたもの
+}
+
+impl CostModel {
+    // 耐久値の分布を N(mu, sigma^2) の (z, 重み) で離散化する
+    const NORMAL_POINTS: [(f64, f64); 5] = [(-2.0, 0.054), (-1.0, 0.242), (0.0, 0.399), (1.0, 0.242), (2.0, 0.054)];
+    const MIN_DURABILITY: i32 = 10;
+    const MAX_DURABILITY: i32 = 5000;
+    // Cごとの risk_lambda、Cが大きいほど外したときに叩く回数が増えて損なので慎重に
+    const RISK_LAMBDA: [(usize, f64); 8] = [(1, 0.0), (2, 0.0), (4, 0.05), (8, 0.1), (16, 0.15), (32, 0.2), (64, 0.3), (128, 0.4)];
+
+    fn new(c: usize, backoff: Schedule) -> Self {
+        let sampling_schedule = match c {
+              1 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1300, 1500, 1750, 2000, 2270, 2500, 2875, 3000, 3350, 3700, 4100, 4500, 5000],
+              2 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1300, 1500, 1750, 2000, 2270, 2500, 2875, 3000, 3350, 3700, 4100, 4500, 5000],
+              4 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1400, 1750, 2270, 2875, 3550, 4200, 5000],
+              8 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1400, 1750, 2270, 2875, 3550, 4200, 5000],
+             16 => vec![0, 20, 40, 70, 120, 190, 280, 395, 540, 760, 1080, 1515, 2160, 3000, 4000, 5000],
+             32 => vec![0, 20, 40, 70, 120, 190, 280, 395, 540, 760, 1080, 1515, 2160, 3000, 4000, 5000],
+             64 => vec![0, 30, 90, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
+            // 128 => vec![0, 30, 90, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
+            128 => vec![0, 50, 120, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
+              _ => vec![0, 25, 60, 120, 210, 350, 570, 960, 1600, 2800, 5000],
+        };
+
+        // v を2倍にする
+        let mut schedule = vec![];
+        let mut u = 1;
+        for &e in &sampling_schedule {
+            if let Some(&last) = schedule.last() {
+                u -= 1;
+                if u < 0 {
+                    schedule.push((last + e) / 2);
+                }
+            }
+            schedule.push(e);
+        }
+
+        Self {
+            c: c as i32,
+            backoff,
+            risk_lambda: Self::RISK_LAMBDA.iter().find(|&&(rc, _)| rc == c).map_or(0.0, |&(_, l)| l),
+            sampling_schedule,
+            schedule,
+        }
+    }
+
+    // 残りの耐久値が mu くらいのマスを壊すコスト、推定が外れていそうな分 (sigma) だけ高く見る
+    fn linear_cost(&self, mu: i32, sigma: i32) -> i32 {
+        mu + self.c + (self.risk_lambda * sigma as f64) as i32
+    }
+
+    // 隣接マスが累積 real で壊れたとき、schedule のどこから叩き始めるか
+    // real を越えない最大の schedule[i] から、backoff なら1つ手前
+    fn start_index(&self, real: i32, backoff: bool) -> usize {
+        let v = &self.schedule;
+        let mut i = 0;
+        while i < v.len() - 1 && v[i + 1] <= real {
+            i += 1;
+        }
+        if i > 1 && backoff {
+            i -= 1;
+        }
+        i
+    }
+
+    // schedule[start] から叩くときの、1回ごとの power
+    fn powers(&self, start: usize) -> impl Iterator<Item = i32> + '_ {
+        let v = &self.schedule;
+        let first = if start > 0 { Some(v[start]) } else { None };
+        first.into_iter().chain((start..v.len() - 1).map(move |i| v[i + 1] - v[i]))
+    }
+
+    // 耐久値 durability のマスを schedule[start] から叩いて壊すときのコスト
+    fn hit_cost(&self, durability: i32, start: usize) -> i32 {
+        let v = &self.schedule;
+        let mut j = start;
+        while j < v.len() - 1 && v[j] < durability {
+            j += 1;
+        }
+        let hits = (j - start) as i32 + if start > 0 { 1 } else { 0 };
+        self.c * hits + v[j]
+    }
+
+    // 耐久値が N(mu, sigma^2) のマスを壊すときのコストの期待値
+    // 隣接マスも同じくらいの耐久値 (real ≒ mu) で壊れていると仮定して叩き始める位置を決める
+    fn expected_cost(&self, mu: i32, sigma: i32) -> f64 {
+        let starts = match self.backoff {
+            Schedule::Backoff => vec![(self.start_index(mu, true), 1.0)],
+            Schedule::Mixed => vec![(self.start_index(mu, true), 0.5), (self.start_index(mu, false), 0.5)],
+        };
+        let weight_sum = Self::NORMAL_POINTS.iter().map(|&(_, w)| w).sum::<f64>();
+        let mut res = 0.0;
+        for &(z, w) in &Self::NORMAL_POINTS {
+            let d = (mu as f64 + z * sigma as f64).round() as i32;
+            let d = d.clamp(Self::MIN_DURABILITY, Self::MAX_DURABILITY);
+            for &(start, p) in &starts {
+                res += w / weight_sum * p * self.hit_cost(d, start) as f64;
+            }
+        }
+        res
+    }
+}
+
+// 最短路で使う優先度付きキュー、(距離, マスの index) を距離の小さい順に取り出す
+pub(crate) trait DistQueue: Default {
+    fn push(&mut self, d: i32, i: usize);
+    fn pop(&mut self) -> Option<(i32, usize)>;
+    // 次に取り出される距離
+    #[cfg(any(test, feature = "local"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn peek(&mut self) -> Option<i32>;
+    #[cfg(any(test, feature = "local"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn clear(&mut self);
+}
+
+// 二分ヒープ、取り出す距離が単調でなくてもよい (A* はこちら)
+#[derive(Default)]
+pub(crate) struct BinaryQueue(std::collections::BinaryHeap<std::cmp::Reverse<(i32, usize)>>);
+
+impl DistQueue for BinaryQueue {
+    fn push(&mut self, d: i32, i: usize) {
+        self.0.push(std::cmp::Reverse((d, i)));
+    }
+
+    fn pop(&mut self) -> Option<(i32, usize)> {
+        self.0.pop().map(|std::cmp::Reverse(v)| v)
+    }
+
+    #[cfg(any(test, feature = "local"))]
+    fn peek(&mut self) -> Option<i32> {
+        self.0.peek().map(|r| (r.0).0)
+    }
+
+    #[cfg(any(test, feature = "local"))]
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+// Radix heap、最後に取り出した距離以上しか push できない (ダイクストラ向け)
+// 1回の push, pop は距離のビット数に比例する
+// テストとベンチだけで使う (手元ビルドの main では使わない)
+#[cfg(any(test, feature = "local"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) struct RadixHeap {
+    last: u32,
+    len: usize,
+    buckets: [Vec<(u32, usize)>; 33], // buckets[b] には last と最上位の異なるビットが b - 1 のもの
+}
+
+#[cfg(any(test, feature = "local"))]
+impl Default for RadixHeap {
+    fn default() -> Self {
+        Self {
+            last: 0,
+            len: 0,
+            buckets: std::array::from_fn(|_| vec![]),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "local"))]
+#[cfg_attr(not(test), allow(dead_code))]
+impl RadixHeap {
+    fn bucket(d: u32, last: u32) -> usize {
+        (32 - (d ^ last).leading_zeros()) as usize
+    }
+
+    // buckets[0] が空なら、最小の要素を含むバケツを last を更新して振り分け直す
+    fn refill(&mut self) {
+        if self.len == 0 || !self.buckets[0].is_empty() {
+            return;
+        }
+        let b = self.buckets.iter().position(|v| !v.is_empty()).unwrap();
+        let items = std::mem::take(&mut self.buckets[b]);
+        self.last = items.iter().map(|&(d, _)| d).min().unwrap();
+        for (d, i) in items {
+            self.buckets[Self::bucket(d, self.last)].push((d, i));
+        }
+    }
+}
+
+#[cfg(any(test, feature = "local"))]
+impl DistQueue for RadixHeap {
+    fn push(&mut self, d: i32, i: usize) {
+        debug_assert!(d as u32 >= self.last, "RadixHeap: {} < {}", d, self.last);
+        self.len += 1;
+        self.buckets[Self::bucket(d as u32, self.last)].push((d as u32, i));
+    }
+
+    fn pop(&mut self) -> Option<(i32, usize)> {
+        self.refill();
+        let (d, i) = self.buckets[0].pop()?;
+        self.len -= 1;
+        Some((d as i32, i))
+    }
+
+    #[cfg(any(test, feature = "local"))]
+    fn peek(&mut self) -> Option<i32> {
+        self.refill();
+        self.buckets[0].last().map(|&(d, _)| d as i32)
+    }
+
+    #[cfg(any(test, feature = "local"))]
+    fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(|v| v.clear());
+        self.last = 0;
+        self.len = 0;
+    }
+}
+
+// 1点間の探索で使い回すバッファ、[0] が始点側、[1] が終点側
+#[cfg(any(test, feature = "local"))]
+#[cfg_attr(not(test), allow(dead_code))]
+struct SearchBuffer<Q: DistQueue = BinaryQueue> {
+    stamp: u32,
+    seen: [Grid<u32>; 2], // seen[side][i] == stamp なら dist, par が有効
+    dist: [Grid<i32>; 2],
+    par: [Grid<u8>; 2],
+    que: [Q; 2],
+    // A* のヒューリスティック用
+    min_cost: i32, // 未破壊マスのコストの最小値
+    broken: i32,   // 破壊済マスの個数
+}
+
+#[cfg(any(test, feature = "local"))]
+#[cfg_attr(not(test), allow(dead_code))]
+impl<Q: DistQueue> SearchBuffer<Q> {
+    fn new(n: usize) -> Self {
+        Self {
+            stamp: 0,
+            seen: [Grid::new(n, 0), Grid::new(n, 0)],
+            dist: [Grid::new(n, 0), Grid::new(n, 0)],
+            par: [Grid::new(n, NO_PARENT), Grid::new(n, NO_PARENT)],
+            que: [Q::default(), Q::default()],
+            min_cost: 0,
+            broken: 0,
+        }
+    }
+
+    // field の状態からヒューリスティックの係数を計算する、マスを壊したら呼び直す
+    fn prepare(&mut self, field: &Field) {
+        self.min_cost = i32::MAX;
+        self.broken = 0;
+        for p in field.is_broken.positions() {
+            if field.is_broken[p] {
+                self.broken += 1;
+            } else {
+                self.min_cost = std::cmp::min(self.min_cost, field.cell_cost(p));
+            }
+        }
+        if self.min_cost == i32::MAX {
+            self.min_cost = 0;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            self.seen.iter_mut().for_each(|v| v.fill(0));
+            self.stamp = 1;
+        }
+        self.que.iter_mut().for_each(|q| q.clear());
+    }
+
+    fn get(&self, side: usize, i: usize) -> i32 {
+        if self.seen[side][i] == self.stamp {
+            self.dist[side][i]
+        } else {
+            i32::MAX
+        }
+    }
+
+    fn set(&mut self, side: usize, i: usize, d: i32, par: u8) {
+        self.seen[side][i] = self.stamp;
+        self.dist[side][i] = d;
+        self.par[side][i] = par;
+    }
+
+    // p から親を辿る、親がいないマスに着くか n * n 回辿ったら終わる
+    fn trace(&self, side: usize, p: Pos) -> impl Iterator<Item = Pos> + '_ {
+        let n = self.par[side].n();
+        let mut pos = Some(p);
+        std::iter::from_fn(move || {
+            let p = pos?;
+            let i = self.par[side].flat_index(p);
+            let d = if self.seen[side][i] == self.stamp { self.par[side][i] } else { NO_PARENT };
+            pos = if d == NO_PARENT { None } else { p.back(d, n) };
+            Some(p)
+        })
+        .take(n * n)
+    }
+}
+
+// ケースごとの解き方、(W, K, C) から Strategy::TABLE で選ぶ
+// 手元では STRATEGY=sampling=Lattice20,search=Greedy のように一部だけ差し替えられる (tune.py が使う)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Strategy {
+    pub(crate) sampling: SamplingPlan,
+    pub(crate) estimator: Estimator,
+    pub(crate) cost: PathCost,
+    pub(crate) schedule: Schedule,
+    pub(crate) search: Search,
+}
+
+// 叩く格子点の取り方、どれも市松模様の片方だけ叩く
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum SamplingPlan {
+    Lattice11, // 7 から 11 おき
+    Lattice12, // 8 から 12 おき
+    Lattice20, // 10 から 20 おき
+}
+
+impl SamplingPlan {
+    pub(crate) const ALL: [SamplingPlan; 3] = [SamplingPlan::Lattice11, SamplingPlan::Lattice12, SamplingPlan::Lattice20];
+
+    // 格子点の y (x) 座標
+    fn lattice(self, n: usize) -> Vec<usize> {
+        match self {
+            SamplingPlan::Lattice11 => (7..n).step_by(11).collect(),
+            SamplingPlan::Lattice12 => (8..n).step_by(12).collect(),
+            SamplingPlan::Lattice20 => (10..n).step_by(20).collect(),
+        }
+    }
+}
+
+// サンプリングしていないマスの推定、一番近いサンプリング点の値をならす
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Estimator {
+    Nearest, // ならさない
+    Smooth,  // 以前の4近傍平均15回と同じくらい (1軸あたりの分散 7.5)
+    Wide,    // guess_field の可視化と同じ広さ
+}
+
+impl Estimator {
+    pub(crate) const ALL: [Estimator; 3] = [Estimator::Nearest, Estimator::Smooth, Estimator::Wide];
+
+    fn smoothing(self) -> Option<Smoothing> {
+        match self {
+            Estimator::Nearest => None,
+            Estimator::Smooth => Some(Smoothing::gaussian(2.74, 3)),
+            Estimator::Wide => Some(Smoothing::gaussian(3.87, 3)),
+        }
+    }
+}
+
+// 推定した耐久値から、経路探索で使う未破壊マスのコストを作る
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PathCost {
+    Linear,   // 残りの耐久値 + C + Cごとの risk_lambda * sigma
+    Expected, // 耐久値を N(guess, sigma^2) として、掘るときの叩き方でかかるコストの期待値 (今は Linear より悪い)
+}
+
+impl PathCost {
+    pub(crate) const ALL: [PathCost; 2] = [PathCost::Linear, PathCost::Expected];
+}
+
+// 経路を掘るとき、隣接マスの耐久値からどこで叩き始めるか
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Schedule {
+    Backoff, // いつも1つ手前から
+    Mixed,   // 半々で1つ手前から (C が大きいと叩きすぎより回数が痛い)
+}
+
+impl Schedule {
+    pub(crate) const ALL: [Schedule; 2] = [Schedule::Backoff, Schedule::Mixed];
+}
+
+// 木の探索
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Search {
+    Greedy,    // 初期解をそのまま使う
+    Tempering, // レプリカ交換法
+}
+
+impl Search {
+    pub(crate) const ALL: [Search; 2] = [Search::Greedy, Search::Tempering];
+}
+
+// 両端を含む範囲
+type Bounds = (usize, usize);
+
+// Debug の名前で選ぶ
+fn by_name<T: Copy + std::fmt::Debug>(all: &[T], name: &str) -> Option<T> {
+    all.iter().copied().find(|v| format!("{:?}", v) == name)
+}
+
+impl Strategy {
+    // (W, K, C) の範囲ごとの戦略、上から順に最初に当てはまるもの
+    // tune.py がバッチの結果から begin と end の間を書き換える
+    // strategy table begin
+    const TABLE: &'static [(Bounds, Bounds, Bounds, Strategy)] = &[
+        ((1, 4), (1, 10), (1, 32), Strategy { sampling: SamplingPlan::Lattice12, estimator: Estimator::Smooth, cost: PathCost::Linear, schedule: Schedule::Backoff, search: Search::Tempering }),
+        ((1, 4), (1, 10), (64, 128), Strategy { sampling: SamplingPlan::Lattice12, estimator: Estimator::Smooth, cost: PathCost::Linear, schedule: Schedule::Mixed, search: Search::Tempering }),
+    ];
+    // strategy table end
+
+    pub(crate) fn select(w: usize, k: usize, c: usize) -> Strategy {
+        let within = |v: usize, (l, r): Bounds| l <= v && v <= r;
+        Self::TABLE
+            .iter()
+            .find(|&&(rw, rk, rc, _)| within(w, rw) && within(k, rk) && within(c, rc))
+            .map_or(Self::TABLE[0].3, |&(_, _, _, strategy)| strategy)
+    }
+
+    // "sampling=Lattice20,search=Greedy" の部分だけ差し替える
+    pub(crate) fn with_overrides(mut self, spec: &str) -> Result<Strategy, String> {
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            let (key, name) = item.split_once('=').ok_or_else(|| format!("expected key=value: {}", item))?;
+            let ok = match key {
+                "sampling" => by_name(&SamplingPlan::ALL, name).map(|v| self.sampling = v),
+                "estimator" => by_name(&Estimator::ALL, name).map(|v| self.estimator = v),
+                "cost" => by_name(&PathCost::ALL, name).map(|v| self.cost = v),
+                "schedule" => by_name(&Schedule::ALL, name).map(|v| self.schedule = v),
+                "search" => by_name(&Search::ALL, name).map(|v| self.search = v),
+                _ => return Err(format!("unknown key: {}", key)),
+            };
+            ok.ok_or_else(|| format!("unknown {}: {}", key, name))?;
+        }
+        Ok(self)
+    }
+}
+
+// TABLE にそのまま書ける形で出す
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Strategy {{ sampling: SamplingPlan::{:?}, estimator: Estimator::{:?}, cost: PathCost::{:?}, schedule: Schedule::{:?}, search: Search::{:?} }}",
+            self.sampling, self.estimator, self.cost, self.schedule, self.search
+        )
+    }
+}
+
+// 手元用、本当の耐久値を使って、推定・木・掘り方のどこで損しているかを切り分ける
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Oracle {
+    None,  // 普通に解く
+    Plan,  // 叩かずに本当の耐久値で木を作り、掘り方は普通
+    Exact, // 本当の耐久値で木を作り、各マスを耐久値ちょうどの1回で壊す (コストの下限)
+}
+
+pub(crate) struct Field {
+    n: usize,
+    w: usize,
+    k: usize,
+    c: usize,
+    guess: Grid<i32>,
+    is_broken: Grid<bool>,
+    real: Grid<i32>,
+    hits: Grid<u32>,  // 叩いた回数
+    sigma: Grid<i32>, // guess の標準偏差の見積もり
+    model: CostModel,
+    plan_cost: Grid<i32>, // 経路探索で使う、未破壊マスを壊すコストの見積もり
+    cost: CostBreakdown,
+    purpose: Purpose, // 今叩いているのが何のためか
+    pub(crate) sampling: Vec<Pos>, // 水源、家 + 一定間隔で取得したpos
+    checks: Vec<Pos>,              // 耐久値が(下限として)分かっているpos
+    dist: Vec<i32>, // sampling 間の距離、dist[s * sampling.len() + t]
+    path_tree: Vec<Grid<u8>>, // sampling[s] からの最短路木、各マスについて親に向かう方向(DYXの逆向き)
+    houses_idx: Vec<usize>,
+    sources_idx: Vec<usize>,
+    near: Vec<Vec<usize>>, // sampling[i] から近い順の sampling の index
+    oracle: Oracle,
+    strategy: Strategy,
+    pub(crate) plan: Option<Plan>, // 探索し終わって掘った木
+    completed: bool, // ジャッジに全ての家がつながったと言われた
+}
+
+impl Field {
+    // Swap で入れ替え先の候補にする近傍の数
+    const NEAR_SIZE: usize = 12;
+    // Shift で動かす先の候補にする近傍の数
+    const SHIFT_SIZE: usize = 4;
+    // 耐久値が分かっているマスから距離 d 離れたマスの標準偏差を SIGMA_UNIT * sqrt(d) とする
+    // (隣接マスの差は平均100くらい)
+    const SIGMA_UNIT: f64 = 100.0;
+    const SIGMA_MAX: i32 = 2000;
+
+    pub(crate) fn new(n: usize, w: usize, k: usize, c: usize, strategy: Strategy) -> Self {
+        Self {
+            n, w, k, c, guess: Grid::new(n, 0), is_broken: Grid::new(n, false), real: Grid::new(n, 0), hits: Grid::new(n, 0), sigma: Grid::new(n, 0),
+            model: CostModel::new(c, strategy.schedule), plan_cost: Grid::new(n, 0), cost: CostBreakdown::default(), purpose: Purpose::Terminal, sampling: vec![], checks: vec![], dist: vec![], path_tree: vec![],
+            houses_idx: vec![], sources_idx: vec![], near: vec![], oracle: Oracle::None, strategy, plan: None, completed: false,
+        }
+    }
+
+    // 水源、家、格子点を sampling に入れる、叩く格子点(市松模様の片方)を返す
+    fn add_sampling(&mut self, sources: &[Pos], houses: &[Pos]) -> Vec<Pos> {
+        for &p in sources {
+            self.sources_idx.push(self.sampling.len());
+            self.sampling.push(p);
+        }
+        for &p in houses {
+            self.houses_idx.push(self.sampling.len());
+            self.sampling.push(p);
+        }
+
+        // let step = (8..self.n).step_by(12).collect::<Vec<_>>();
+        // let step = 
+        //     if self.k >= 9 {
+        //         (7..self.n).step_by(12).collect::<Vec<_>>()
+        //     // } else if self.w <= 1 {
+        //         // (12..self.n).step_by(25).collect::<Vec<_>>()
+        //     } else {
+        //         (10..self.n).step_by(20).collect::<Vec<_>>()
+        //     };
+
+        // let step = (10..self.n).step_by(20).collect::<Vec<_>>();
+        let step = self.strategy.sampling.lattice(self.n);
+        let mut steps = vec![];
+        let mut f1 = true;
+        for &y in &step {
+            f1 ^= true;
+            let mut f2 = true;
+            for &x in &step {
+                f2 ^= true;
+                self.sampling.push(Pos::new(y, x));
+                if f1 ^ f2 {
+                    continue;
+                }
+                steps.push(Pos::new(y, x));
+            }
+        }
+        steps
+    }
+
+    // init、水源、家、格子点を叩いてサンプリングする
+    pub(crate) fn guess_field<J: Judge>(&mut self, sources: &[Pos], houses: &[Pos], judge: &mut J) -> Result<(), SolverError> {
+        let steps = self.add_sampling(sources, houses);
+        self.purpose = Purpose::Terminal;
+        let mut checks = vec![];
+        for &p in sources.iter().chain(houses) {
+            self.guess[p] = self.destruct(p, true, houses, judge)?;
+            checks.push(p);
+        }
+
+        let arrowed_min_dist = 5;
+        let rejected_min_dist = 75;
+        // べつに、サンプリングしていない点でもそれを使ってごにょごにょしていいじゃん！
+        // ただ、これやったところで誤差レベル...？
+        self.purpose = Purpose::Lattice;
+        for &p in &steps {
+            let min_dist = checks.iter().map(|&c| c.manhattan(p)).min().unwrap();
+            if min_dist <= arrowed_min_dist {
+                continue;
+            }
+            // 一番近いhouses, sourcesが規定値以上離れてるならサボる
+            let near_house_dist = houses.iter().map(|&c| c.manhattan(p)).min().unwrap();
+            let near_source_dist = sources.iter().map(|&c| c.manhattan(p)).min().unwrap();
+            if near_house_dist >= rejected_min_dist && near_source_dist >= rejected_min_dist {
+                checks.push(p);
+                self.guess[p] = 4500;
+                continue;
+            }
+            self.guess[p] = self.destruct(p, true, &[], judge)?;
+            checks.push(p);
+        }
+        self.checks = checks;
+        Ok(())
+    }
+
+    // 叩かずに本当の耐久値をそのまま guess にする、推定の誤差はないので sigma は 0
+    // Exact なら1回で壊せるので、plan_cost も耐久値 + C ちょうど
+    pub(crate) fn guess_oracle<J: Judge>(&mut self, sources: &[Pos], houses: &[Pos], judge: &J, oracle: Oracle) -> Result<(), SolverError> {
+        self.oracle = oracle;
+        self.add_sampling(sources, houses);
+        for p in self.guess.positions() {
+            self.guess[p] = judge.durability(p).ok_or(SolverError::NoOracle)?;
+        }
+        self.checks = self.sampling.clone();
+        self.update_plan_cost();
+        if oracle == Oracle::Exact {
+            self.plan_cost = self.guess.map(|&d| d + self.c as i32);
+        }
+        Ok(())
+    }
+
+    // サンプリングした値から全体の耐久値を推定する
+    pub(crate) fn guess_estimate(&mut self) {
+        let checks = &self.checks;
+        for p in self.guess.positions() {
+            if checks.contains(&p) {
+                continue;
+            }
+            // 一番近いchecksの値を採用
+            let &q = checks.iter().min_by_key(|&&c| c.manhattan(p)).unwrap();
+            self.guess[p] = self.guess[q];
+        }
+        // 壊して耐久値が分かったマスは動かさない
+        if let Some(smoothing) = self.strategy.estimator.smoothing() {
+            self.guess = smoothing.apply(&self.guess, Some(&self.is_broken));
+        }
+        self.guess_sigma();
+        self.update_plan_cost();
+    }
+
+    pub(crate) fn build_paths(&mut self, threads: usize) {
+        // sampling の各点から各点へのdist, ... を求めておく
+        for (dist, par) in self.all_pairs(threads) {
+            self.dist.extend(dist);
+            self.path_tree.push(par);
+        }
+
+        // 近傍操作用に、各sampling点から近い順の格子点を覚えておく
+        for &p in &self.sampling {
+            let mut near = (0..self.sampling.len()).collect::<Vec<_>>();
+            near.sort_by_key(|&i| self.sampling[i].manhattan(p));
+            // 先頭は自分自身(と同じ座標の点)
+            near.retain(|&i| self.sampling[i] != p);
+            near.truncate(Self::NEAR_SIZE);
+            self.near.push(near);
+        }
+
+        // 頂点集合idとそれぞれの距離のみ見ながら、それらのpathを(s, t) のみ管理してufでmerge管理...すればいいかんじ？
+        // 焼きなましで高々115個の頂点のみを見ればよいのでうれしい
+    }
+
+    // sampling の各点からの dijkstra_vec を sampling の順に返す
+    // threads > 1 なら sampling を連続する塊に分けてスレッドごとに計算し、塊の順につなげる
+    pub(crate) fn all_pairs(&self, threads: usize) -> Vec<(Vec<i32>, Grid<u8>)> {
+        let threads = threads.clamp(1, self.sampling.len().max(1));
+        if threads == 1 {
+            return self.sampling.iter().map(|&s| self.dijkstra_vec(s, &self.sampling)).collect();
+        }
+        let chunk = self.sampling.len().div_ceil(threads);
+        std::thread::scope(|scope| {
+            let handles = self
+                .sampling
+                .chunks(chunk)
+                .map(|starts| scope.spawn(move || starts.iter().map(|&s| self.dijkstra_vec(s, &self.sampling)).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        })
+    }
+
+    // 破壊して耐久値が分かったマスからの距離で、各マスの guess の標準偏差を見積もる
+    // 叩いたけど壊れなかったマス、サボったマスは下限しか分かっていないので使わない
+    fn guess_sigma(&mut self) {
+        let mut dist = Grid::new(self.n, usize::MAX);
+        let mut que = std::collections::VecDeque::new();
+        for p in self.is_broken.positions() {
+            if self.is_broken[p] {
+                dist[p] = 0;
+                que.push_back(p);
+            }
+        }
+        while let Some(p) = que.pop_front() {
+            for (_, q) in dist.neighbors(p) {
+                if dist[q] == usize::MAX {
+                    dist[q] = dist[p] + 1;
+                    que.push_back(q);
+                }
+            }
+        }
+        self.sigma = dist.map(|&d| {
+            if d == usize::MAX {
+                Self::SIGMA_MAX
+            } else {
+                std::cmp::min(Self::SIGMA_MAX, (Self::SIGMA_UNIT * (d as f64).sqrt()) as i32)
+            }
+        });
+    }
+
+    // 手元用、マスごとの叩いた回数、累積power、本当の耐久値を出力する (waste.py で可視化する)
+    fn waste_output(&self, judge: &LocalJudge, path: &str) -> std::io::Result<()> {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(w, "{} {}", self.n, self.c)?;
+        fn write_grid<T: std::fmt::Display, W: Write>(w: &mut W, grid: &Grid<T>) -> std::io::Result<()> {
+            for row in grid.rows() {
+                writeln!(w, "{}", row.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" "))?;
+            }
+            Ok(())
+        }
+        write_grid(&mut w, &self.hits)?;
+        write_grid(&mut w, &self.real)?;
+        write_grid(&mut w, &judge.durability)?;
+        w.flush()
+    }
+
+    // 推定した耐久値を入力と同じ形式でダンプする (Debug のときだけ)
+    fn guess_output(&self, sources: &[Pos], houses: &[Pos]) {
+        log(Level::Debug, "guess", format_args!("{}", self.guess_dump(sources, houses)));
+    }
+
+    fn guess_dump(&self, sources: &[Pos], houses: &[Pos]) -> String {
+        let mut lines = vec![format!("{} {} {} {}", self.n, self.w, self.k, self.c)];
+        lines.extend(self.guess.rows().map(|row| row.iter().map(|&x| x.to_string()).collect::<Vec<_>>().join(" ")));
+        lines.extend(sources.iter().chain(houses).map(|p| p.to_string()));
+        lines.join("\n")
+    }
+
+    // s から t への最短路をA*で求める、path は t から s の順
+    // 事前に buf.prepare(self) しておくこと
+    #[cfg(any(test, feature = "local"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn astar(&self, s: Pos, t: Pos, buf: &mut SearchBuffer) -> (i32, Path) {
+        let (broken, min_cost) = (buf.broken, buf.min_cost);
+        let h = |p: Pos| {
+            // 破壊済マスはコスト0なので、その分だけ引いておけば許容的
+            std::cmp::max(0, t.manhattan(p) as i32 - broken) * min_cost
+        };
+        buf.reset();
+        let si = self.guess.flat_index(s);
+        let ti = self.guess.flat_index(t);
+        buf.set(0, si, 0, NO_PARENT);
+        buf.que[0].push(h(s), si);
+        while let Some((f, i)) = buf.que[0].pop() {
+            let p = self.guess.pos(i);
+            let d = buf.get(0, i);
+            if f > d + h(p) {
+                continue;
+            }
+            if i == ti {
+                break;
+            }
+            for (k, q) in p.neighbors(self.n) {
+                let qi = self.guess.flat_index(q);
+                let nd = d + self.cell_cost(q);
+                if buf.get(0, qi) <= nd {
+                    continue;
+                }
+                buf.set(0, qi, nd, k);
+                buf.que[0].push(nd + h(q), qi);
+            }
+        }
+        (buf.get(0, ti), buf.trace(0, t).collect())
+    }
+
+    // s から t への最短路を両側からのダイクストラで求める、path は t から s の順
+    #[cfg(any(test, feature = "local"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn bidirectional<Q: DistQueue>(&self, s: Pos, t: Pos, buf: &mut SearchBuffer<Q>) -> (i32, Path) {
+        buf.reset();
+        let si = self.guess.flat_index(s);
+        let ti = self.guess.flat_index(t);
+        // コストはマスに入るときにかかるので、t側の dist は「そのマスから t まで(そのマス自身は含まない)」
+        buf.set(0, si, 0, NO_PARENT);
+        buf.set(1, ti, 0, NO_PARENT);
+        buf.que[0].push(0, si);
+        buf.que[1].push(0, ti);
+        let mut best = if si == ti { 0 } else { i32::MAX };
+        let mut meet = s;
+        loop {
+            let (f0, f1) = (buf.que[0].peek().unwrap_or(i32::MAX), buf.que[1].peek().unwrap_or(i32::MAX));
+            if f0 == i32::MAX || f1 == i32::MAX || f0.saturating_add(f1) >= best {
+                break;
+            }
+            let side = if f0 <= f1 { 0 } else { 1 };
+            let (d, i) = buf.que[side].pop().unwrap();
+            if d > buf.get(side, i) {
+                continue;
+            }
+            let p = self.guess.pos(i);
+            for (k, q) in p.neighbors(self.n) {
+                let qi = self.guess.flat_index(q);
+                let nd = d + if side == 0 { self.cell_cost(q) } else { self.cell_cost(p) };
+                if buf.get(side, qi) <= nd {
+                    continue;
+                }
+                buf.set(side, qi, nd, k);
+                buf.que[side].push(nd, qi);
+                let other = buf.get(1 - side, qi);
+                if other != i32::MAX && nd + other < best {
+                    best = nd + other;
+                    meet = q;
+                }
+            }
+        }
+        // meet から t まで、meet から s までをそれぞれ辿ってつなげる
+        let mut path = buf.trace(1, meet).collect::<Vec<_>>();
+        path.reverse();
+        path.extend(buf.trace(0, meet).skip(1));
+        (best, path)
+    }
+
+    // 破壊済マスはコスト0、それ以外は推定される残りの耐久値 + C
+    fn cell_cost(&self, p: Pos) -> i32 {
+        if self.is_broken[p] {
+            return 0;
+        }
+        self.plan_cost[p]
+    }
+
+    // guess, sigma から plan_cost を計算し直す
+    fn update_plan_cost(&mut self) {
+        for p in self.plan_cost.positions() {
+            let mu = std::cmp::max(1, self.guess[p] - self.real[p]);
+            self.plan_cost[p] = match self.strategy.cost {
+                PathCost::Linear => self.model.linear_cost(mu, self.sigma[p]),
+                // 外れたときに叩く回数が増える分は期待値に入っているので risk_lambda は足さない
+                PathCost::Expected => self.model.expected_cost(mu, self.sigma[p]).round() as i32,
+            };
+        }
+    }
+
+    // s から v の各点への距離と、s を根とする最短路木(各マスの親の方向)を返す
+    // 200x200 の盤面では二分ヒープの方が速かった (make bench の dijkstra_vec all binary / radix)
+    pub(crate) fn dijkstra_vec(&self, s: Pos, v: &[Pos]) -> (Vec<i32>, Grid<u8>) {
+        self.dijkstra_vec_with::<BinaryQueue>(s, v)
+    }
+
+    // キューを選んで dijkstra_vec する
+    pub(crate) fn dijkstra_vec_with<Q: DistQueue>(&self, s: Pos, v: &[Pos]) -> (Vec<i32>, Grid<u8>) {
+        let mut dist = Grid::new(self.n, i32::MAX);
+        // コスト0のマスがあるので、distからではなく親を覚えて復元する
+        let mut par = Grid::new(self.n, NO_PARENT);
+        let mut que = Q::default();
+        que.push(0, dist.flat_index(s));
+        dist[s] = 0;
+        while let Some((d, i)) = que.pop() {
+            if d > dist[i] {
+                continue;
+            }
+            let p = dist.pos(i);
+            for (k, q) in p.neighbors(self.n) {
+                let nd = d + self.cell_cost(q);
+                if dist[q] <= nd {
+                    continue;
+                }
+                dist[q] = nd;
+                par[q] = k;
+                que.push(nd, dist.flat_index(q));
+            }
+        }
+        (v.iter().map(|&p| dist[p]).collect(), par)
+    }
+
+    fn dist(&self, s: usize, t: usize) -> i32 {
+        self.dist[s * self.sampling.len() + t]
+    }
+
+    // sampling[t] から sampling[s] までの path を辿る
+    fn path_iter(&self, s: usize, t: usize) -> PathIter<'_> {
+        PathIter {
+            par: &self.path_tree[s],
+            pos: Some(self.sampling[t]),
+        }
+    }
+
+    fn path(&self, s: usize, t: usize) -> Path {
+        self.path_iter(s, t).collect()
+    }
+
+    fn query<J: Judge>(&mut self, p: Pos, power: i32, judge: &mut J) -> Result<Responce, SolverError> {
+        // つながったあとはもう叩かない
+        if self.completed {
+            return Ok(Responce::Completed);
+        }
+        if self.is_broken[p] {
+            return Ok(Responce::Broken);
+        }
+        self.real[p] += power;
+        self.hits[p] += 1;
+        let res = judge.query(p, power)?;
+        let broken = !matches!(res, Responce::NotBroken);
+        // 壊れたマスは、本当の耐久値が分かれば叩きすぎた分を数える
+        let overshoot = judge.durability(p).filter(|_| broken).map(|d| self.real[p] - d);
+        self.cost.add(self.purpose, self.c, power, overshoot);
+        if broken {
+            self.is_broken[p] = true;
+        }
+        self.completed |= matches!(res, Responce::Completed);
+        Ok(res)
+    }
+
+    // guess == false ならhousesは不要、&[]でよい
+    fn destruct<J: Judge>(&mut self, p: Pos, guess: bool, houses: &[Pos], judge: &mut J) -> Result<i32, SolverError> {
+        if self.is_broken[p] {
+            return Ok(self.real[p]);
+        }
+
+        if guess {
+            let v = &self.model.sampling_schedule;
+            // house なら破壊する
+            let lim = if houses.contains(&p) {
+                5000
+            } else {
+                500
+            };
+
+            // 最後サボる
+            let powers = (0..v.len() - 1).take_while(|&i| v[i + 1] < lim).map(|i| v[i + 1] - v[i]).collect::<Vec<_>>();
+            for power in powers {
+                self.query(p, power, judge)?;
+            }
+            if self.is_broken[p] {
+                return Ok(self.real[p]);
+            } 
+            return Ok(4500);
+        } 
+
+        if self.oracle == Oracle::Exact {
+            // 残りの耐久値ちょうどで1回叩く
+            let durability = judge.durability(p).ok_or(SolverError::NoOracle)?;
+            self.query(p, durability - self.real[p], judge)?;
+            return Ok(self.real[p]);
+        }
+
+        // 隣接マスにrealが有効なものがある -> その値を叩く   
+        let mut start = 0;
+        if let Some((_, q)) = p.neighbors(self.n).find(|&(_, q)| self.is_broken[q]) {
+            let backoff = match self.strategy.schedule {
+                Schedule::Backoff => true,
+                Schedule::Mixed => rand(0, 2) == 1,
+            };
+            start = self.model.start_index(self.real[q], backoff);
+        }
+        let powers = self.model.powers(start).collect::<Vec<_>>();
+        for power in powers {
+            if !matches!(self.query(p, power, judge)?, Responce::NotBroken) {
+                break;
+            }
+        }
+        Ok(self.real[p])
+    }
+
+    fn generate_init_state(&self) -> State {
+        let mut keys = vec![];
+        for &h in &self.houses_idx {
+            keys.push(h);
+        }
+        for &s in &self.sources_idx {
+            keys.push(s);
+        }
+        let mut res = self.state_generate(&keys);
+
+        for add in 0..self.sampling.len() {
+            if keys.contains(&add) {
+                continue;
+            } 
+            let mut new_keys = keys.clone();
+            new_keys.push(add);
+            let mut new_res = self.state_generate(&keys);
+            if self.state_score(&mut new_res) < self.state_score(&mut res) {
+                res = new_res;
+            }
+        }
+        res
+    }
+
+    // state の通りに掘る、全部つながれば Ok(())
+    fn done<J: Judge>(&mut self, state: &State, judge: &mut J) -> Result<(), SolverError> {
+        log(Level::Info, "excavation", format_args!("start"));
+        self.purpose = Purpose::Excavation;
+        if !state.check(&self.sources_idx, &self.houses_idx, self.sampling.len()) {
+            return Err(SolverError::InvalidState);
+        }
+        let mut break_pos = vec![];
+        for &(s, t) in &state.edges {
+            break_pos.extend(self.path_iter(s, t));
+        }
+        for &p in &break_pos {
+            self.destruct(p, false, &[], judge)?;
+        }
+        if !self.completed {
+            return Err(SolverError::Disconnected);
+        }
+        Ok(())
+    }
+
+    // state を sampling の座標と壊すマスつきで保存できる形にする
+    fn plan(&self, state: &mut State) -> Plan {
+        let mut cells = state.edges.iter().flat_map(|&(s, t)| self.path_iter(s, t)).collect::<Vec<_>>();
+        cells.sort();
+        cells.dedup();
+        Plan {
+            n: self.n,
+            score: self.state_score(state),
+            sampling: self.sampling.clone(),
+            keys: state.keys.clone(),
+            edges: state.edges.clone(),
+            cells,
+        }
+    }
+
+    // 保存した plan を今の sampling に座標で対応させて初期解にする
+    // 全部の辺が対応して家と水源がつながっていればその木を (手で直した木もそのまま使う)、そうでなければ keys から作り直す
+    fn state_from_plan(&self, plan: &Plan) -> State {
+        let index = |i: usize| self.sampling.iter().position(|&p| p == plan.sampling[i]);
+        let edges = plan.edges.iter().map(|&(s, t)| Some((index(s)?, index(t)?))).collect::<Option<Vec<_>>>();
+        let mut keys = self.sources_idx.iter().chain(&self.houses_idx).copied().collect::<Vec<_>>();
+        let ends = edges.iter().flatten().flat_map(|&(s, t)| [s, t]);
+        for i in plan.keys.iter().filter_map(|&i| index(i)).chain(ends) {
+            if !keys.contains(&i) {
+                keys.push(i);
+            }
+        }
+        if let Some(edges) = edges {
+            let state = State::new(&keys, &edges);
+            if state.check(&self.sources_idx, &self.houses_idx, self.sampling.len()) {
+                return state;
+            }
+        }
+        self.state_generate(&keys)
+    }
+
+    fn state_score(&self, state: &mut State) -> i32 {
+        if let Some(v) = state.score {
+            return v
+        }
+        // 辺同士で共有しているマスを二重に数えないよう、壊すマスの和集合でコストを見る
+        let mut cells = vec![];
+        for &(s, t) in &state.edges {
+            cells.extend(self.path_iter(s, t).map(|p| self.guess.flat_index(p)));
+        }
+        cells.sort_unstable();
+        cells.dedup();
+        let res = cells.iter().map(|&i| self.cell_cost(self.guess.pos(i))).sum();
+        state.score = Some(res);
+        res
+    }
+
+    // 使えない近傍だったら Random にする、実際に使った近傍も返す
+    fn claim(&self, state: &State, mv: Move) -> (State, Move) {
+        // 確率で色々する
+        let mut keys = state.keys.clone();
+        let applied = match mv {
+            Move::Random => false,
+            Move::Swap => self.state_swap_key(&mut keys, Self::NEAR_SIZE),
+            Move::Shift => self.state_swap_key(&mut keys, Self::SHIFT_SIZE),
+            Move::Reroute => self.state_reroute_edge(state, &mut keys),
+            Move::Merge => self.state_merge_edges(state, &mut keys),
+        };
+        if applied {
+            return (self.state_generate(&keys), mv);
+        }
+        if rand(0, 2) == 0 {
+            self.state_erase_key(&mut keys);
+        }
+        let cnt = rand(1, 3);
+        for _ in 0..cnt {
+            self.state_add_key(&mut keys);
+        }
+        (self.state_generate(&keys), Move::Random)
+    }
+
+    fn state_add_key(&self, keys: &mut Vec<usize>) {
+        // 0..self.sampling.len() で、state.keysに入っていない値をstate.keysに追加
+        let add = rand(0, self.sampling.len());
+        if keys.iter().all(|&x| x != add) {
+            keys.push(add);
+        }
+    }
+
+    fn state_erase_key(&self, keys: &mut Vec<usize>) {
+        let del = rand(0, keys.len());
+        if !self.is_terminal(keys[del]) {
+            keys.remove(del);
+        }
+    }
+
+    // Steiner点を近くの格子点(近い方から size 個のうちどれか)に置き換える
+    fn state_swap_key(&self, keys: &mut [usize], size: usize) -> bool {
+        let steiner = (0..keys.len()).filter(|&i| !self.is_terminal(keys[i])).collect::<Vec<_>>();
+        if steiner.is_empty() {
+            return false;
+        }
+        let pos = steiner[rand(0, steiner.len())];
+        let candidates = self.near[keys[pos]].iter().take(size).filter(|&x| !keys.contains(x)).collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return false;
+        }
+        keys[pos] = *candidates[rand(0, candidates.len())];
+        true
+    }
+
+    // 辺のpath上の点の近くにある格子点を経由させる
+    fn state_reroute_edge(&self, state: &State, keys: &mut Vec<usize>) -> bool {
+        if state.edges.is_empty() {
+            return false;
+        }
+        let (s, t) = state.edges[rand(0, state.edges.len())];
+        let path = self.path(s, t);
+        // 端点付近は避けて、pathの真ん中半分から選ぶ
+        let pos = path[rand(path.len() / 4, path.len() * 3 / 4 + 1)];
+        self.state_add_near_key(keys, pos)
+    }
+
+    // 2辺の中点の間に新しいSteiner点を置く
+    fn state_merge_edges(&self, state: &State, keys: &mut Vec<usize>) -> bool {
+        if state.edges.len() < 2 {
+            return false;
+        }
+        let a = rand(0, state.edges.len());
+        let b = (a + rand(1, state.edges.len())) % state.edges.len();
+        let (pa, pb) = (self.path_middle(state.edges[a]), self.path_middle(state.edges[b]));
+        self.state_add_near_key(keys, Pos::new((pa.y + pb.y) / 2, (pa.x + pb.x) / 2))
+    }
+
+    fn path_middle(&self, (s, t): (usize, usize)) -> Pos {
+        let path = self.path(s, t);
+        path[path.len() / 2]
+    }
+
+    // pos に近い格子点のうち、keysに入っていないものを1つ追加
+    fn state_add_near_key(&self, keys: &mut Vec<usize>, p: Pos) -> bool {
+        let mut candidates = (0..self.sampling.len()).filter(|i| !keys.contains(i)).collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return false;
+        }
+        candidates.sort_by_key(|&i| self.sampling[i].manhattan(p));
+        let cnt = std::cmp::min(Self::SHIFT_SIZE, candidates.len());
+        keys.push(candidates[rand(0, cnt)]);
+        true
+    }
+
+    fn is_terminal(&self, idx: usize) -> bool {
+        self.houses_idx.contains(&idx) || self.sources_idx.contains(&idx)
+    }
+
+    pub(crate) fn state_generate(&self, keys: &Vec<usize>) -> State {
+        let mut edges = vec![];
+        let mut uf = UnionFind::new(self.sampling.len());
+
+        let mut kruskal_edges = vec![];
+
+        for &s in keys {
+            for &t in keys {
+                kruskal_edges.push((self.dist(s, t), s, t));
+            }
+        }
+        kruskal_edges.sort_by_key(|&(dist, _, _)| dist);
+
+        for &(_, s, t) in &kruskal_edges {
+            if uf.same(s, t) {
+                continue;
+            }
+            let s_is_ok = self.sources_idx.iter().any(|&x| uf.same(x, s));
+            let t_is_ok = self.sources_idx.iter().any(|&x| uf.same(x, t));
+            if s_is_ok && t_is_ok {
+                continue;
+            }
+            uf.merge(s, t);
+            edges.push((s, t));
+        }
+
+        // 葉になっているSteiner点は不要なので取り除く
+        let mut keys = keys.clone();
+        loop {
+            let leaf = keys.iter().position(|&v| {
+                !self.is_terminal(v) && edges.iter().filter(|&&(s, t)| s == v || t == v).count() <= 1
+            });
+            match leaf {
+                Some(i) => {
+                    let v = keys.swap_remove(i);
+                    edges.retain(|&(s, t)| s != v && t != v);
+                }
+                None => break,
+            }
+        }
+        State::new(&keys, &edges)
+    }
+
+}
+
+// 近傍操作の種類
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Move {
+    Random,  // 1つ消して、ランダムに1~2個足す
+    Swap,    // Steiner点を近くの格子点と入れ替える
+    Shift,   // Steiner点を隣の格子点に動かす
+    Reroute, // 辺の途中にある格子点を経由させる
+    Merge,   // 2辺の間に新しいSteiner点を置く
+}
+
+impl Move {
+    const ALL: [Move; 5] = [Move::Random, Move::Swap, Move::Shift, Move::Reroute, Move::Merge];
+
+    fn id(self) -> usize {
+        Self::ALL.iter().position(|&m| m == self).unwrap()
+    }
+}
+
+// 最近の採択率に応じて Move を選ぶ
+struct MoveSelector {
+    weight: Vec<f64>,
+    tried: Vec<usize>,
+    accepted: Vec<usize>,
+    total_tried: Vec<usize>,
+    total_accepted: Vec<usize>,
+}
+
+impl MoveSelector {
+    // この回数試すごとに重みを更新する
+    const WINDOW: usize = 100;
+    // 採択されなくなっても、たまには選ぶ
+    const MIN_WEIGHT: f64 = 0.05;
+    // 古い重みをどれだけ残すか
+    const DECAY: f64 = 0.5;
+
+    fn new() -> Self {
+        let n = Move::ALL.len();
+        Self {
+            weight: vec![1.0; n],
+            tried: vec![0; n],
+            accepted: vec![0; n],
+            total_tried: vec![0; n],
+            total_accepted: vec![0; n],
+        }
+    }
+
+    fn select(&self) -> Move {
+        let sum = self.weight.iter().sum::<f64>();
+        let mut r = rand_f64() * sum;
+        for (i, &w) in self.weight.iter().enumerate() {
+            if r < w {
+                return Move::ALL[i];
+            }
+            r -= w;
+        }
+        Move::ALL[Move::ALL.len() - 1]
+    }
+
+    fn feedback(&mut self, mv: Move, accepted: bool) {
+        let i = mv.id();
+        self.tried[i] += 1;
+        self.total_tried[i] += 1;
+        if accepted {
+            self.accepted[i] += 1;
+            self.total_accepted[i] += 1;
+        }
+        if self.tried.iter().sum::<usize>() < Self::WINDOW {
+            return;
+        }
+        // 採択率の平均で正規化して、重みを更新
+        let rate = (0..Move::ALL.len())
+            .map(|i| (self.accepted[i] as f64 + 1.0) / (self.tried[i] as f64 + 2.0))
+            .collect::<Vec<_>>();
+        let sum = rate.iter().sum::<f64>();
+        for (weight, r) in self.weight.iter_mut().zip(rate) {
+            let w = r / sum * Move::ALL.len() as f64;
+            *weight = (Self::DECAY * *weight + (1.0 - Self::DECAY) * w).max(Self::MIN_WEIGHT);
+        }
+        self.tried.iter_mut().for_each(|x| *x = 0);
+        self.accepted.iter_mut().for_each(|x| *x = 0);
+    }
+
+    fn summary(&self) -> String {
+        Move::ALL
+            .iter()
+            .map(|&m| {
+                let i = m.id();
+                format!("{:?}: {}/{} (w: {:.2})", m, self.total_accepted[i], self.total_tried[i], self.weight[i])
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// 何のためにマスを叩いたか
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Purpose {
+    Terminal,   // 水源、家を壊す (Sampling)
+    Lattice,    // 格子点のサンプリング (Sampling)
+    Excavation, // 経路の掘削 (Excavation)
+}
+
+impl Purpose {
+    const ALL: [Purpose; 3] = [Purpose::Terminal, Purpose::Lattice, Purpose::Excavation];
+
+    fn id(self) -> usize {
+        Self::ALL.iter().position(|&p| p == self).unwrap()
+    }
+
+    fn phase(self) -> Phase {
+        match self {
+            Purpose::Terminal | Purpose::Lattice => Phase::Sampling,
+            Purpose::Excavation => Phase::Excavation,
+        }
+    }
+}
+
+// コストの内訳、Purpose ごとに C の分と power の分を分けて数える
+#[derive(Default, Clone)]
+pub(crate) struct CostBreakdown {
+    pub(crate) hits: [usize; 3],
+    pub(crate) overhead: [usize; 3],  // C * 叩いた回数
+    pub(crate) power: [usize; 3],
+    pub(crate) broken: [usize; 3],    // 壊したマスの数
+    pub(crate) overshoot: [usize; 3], // 壊したマスで、耐久値を超えて叩いた分 (分かるときだけ)
+    pub(crate) known: bool,           // overshoot が分かっているか
+}
+
+impl CostBreakdown {
+    fn add(&mut self, purpose: Purpose, c: usize, power: i32, overshoot: Option<i32>) {
+        let i = purpose.id();
+        self.hits[i] += 1;
+        self.overhead[i] += c;
+        self.power[i] += power as usize;
+        if let Some(overshoot) = overshoot {
+            self.known = true;
+            self.broken[i] += 1;
+            self.overshoot[i] += overshoot as usize;
+        }
+    }
+
+    pub(crate) fn total(&self) -> usize {
+        self.overhead.iter().sum::<usize>() + self.power.iter().sum::<usize>()
+    }
+
+    fn report(&self) {
+        for &purpose in &Purpose::ALL {
+            let i = purpose.id();
+            let overshoot = if self.known { self.overshoot[i].to_string() } else { "-".to_string() };
+            log(
+                Level::Info,
+                "cost",
+                format_args!("purpose: {:?}, phase: {:?}, hits: {}, overhead: {}, power: {}, overshoot: {}", purpose, purpose.phase(), self.hits[i], self.overhead[i], self.power[i], overshoot),
+            );
+        }
+        log(Level::Info, "cost", format_args!("total: {}", self.total()));
+    }
+
+    // score の記録に入れる内訳、run.py で集計するので形式を変えるときは合わせる
+    fn to_json(&self) -> Json {
+        let fields = Purpose::ALL.iter().map(|&purpose| {
+            let i = purpose.id();
+            let overshoot = if self.known { Json::Number(self.overshoot[i] as i64) } else { Json::Null };
+            let values = vec![
+                ("hits", Json::Number(self.hits[i] as i64)),
+                ("overhead", Json::Number(self.overhead[i] as i64)),
+                ("power", Json::Number(self.power[i] as i64)),
+                ("overshoot", overshoot),
+            ];
+            (format!("{:?}", purpose), Json::object(values))
+        });
+        Json::Object(fields.collect())
+    }
+}
+
+// レプリカ交換法の1レプリカ
+struct Replica {
+    state: State,
+    temp: f64,
+    moves: MoveSelector,
+    best: State,
+    accepted: usize,
+    rng: StdRng, // どのスレッドで進めても同じ乱数列になるように、レプリカごとに持つ
+}
+
+impl Replica {
+    fn new(state: &State, temp: f64, seed: u64) -> Self {
+        Self {
+            state: state.clone(),
+            temp,
+            moves: MoveSelector::new(),
+            best: state.clone(),
+            accepted: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // レプリカの乱数に入れ替えて進め、終わったら戻す
+    fn step(&mut self, field: &Field, iter: usize) {
+        swap_rng(&mut self.rng);
+        self.step_with(field, iter);
+        swap_rng(&mut self.rng);
+    }
+
+    fn step_with(&mut self, field: &Field, iter: usize) {
+        for _ in 0..iter {
+            let mv = self.moves.select();
+            let (mut next_state, mv) = field.claim(&self.state, mv);
+            let diff = field.state_score(&mut next_state) - field.state_score(&mut self.state);
+            let accepted = diff < 0 || rand_f64() < (-diff as f64 / self.temp).exp();
+            self.moves.feedback(mv, accepted);
+            if !accepted {
+                continue;
+            }
+            self.accepted += 1;
+            self.state = next_state;
+            if field.state_score(&mut self.state) < field.state_score(&mut self.best) {
+                self.best = self.state.clone();
+            }
+        }
+    }
+}
+
+// レプリカ交換法
+// レプリカをワーカースレッドで並列に進める、レプリカごとに乱数を持つのでスレッド数で結果は変わらない
+struct Tempering {
+    replicas: Vec<Replica>, // temp の低い順
+    best: State,
+    threads: usize,
+    round: usize,
+    exchanged: usize,
+}
+
+impl Tempering {
+    const REPLICAS: usize = 20;
+    // この回数 claim するごとにレプリカ交換する
+    const EXCHANGE_INTERVAL: usize = 20;
+    // 温度は初期解のスコアに対する比で決める
+    const TEMP_HIGH: f64 = 5e-3;
+    const TEMP_LOW: f64 = 5e-5;
+
+    fn new(field: &Field, init_state: &mut State, threads: usize) -> Self {
+        let score = field.state_score(init_state).max(1) as f64;
+        let replicas = (0..Self::REPLICAS)
+            .map(|i| {
+                let r = i as f64 / (Self::REPLICAS - 1) as f64;
+                let temp = score * Self::TEMP_LOW * (Self::TEMP_HIGH / Self::TEMP_LOW).powf(r);
+                // 親スレッドの乱数から種を渡して、seed_rng したときに再現できるようにする
+                Replica::new(init_state, temp, rand_u64())
+            })
+            .collect();
+        Self {
+            replicas,
+            best: init_state.clone(),
+            threads: threads.max(1),
+            round: 0,
+            exchanged: 0,
+        }
+    }
+
+    fn run(&mut self, field: &Field, budget: &mut Budget) {
+        while !budget.is_over() {
+            self.round += 1;
+            self.step(field);
+            self.exchange(field);
+            budget.tick(self.replicas.len() * Self::EXCHANGE_INTERVAL);
+        }
+        for replica in &mut self.replicas {
+            if field.state_score(&mut replica.best) < field.state_score(&mut self.best) {
+                self.best = replica.best.clone();
+            }
+        }
+    }
+
+    fn step(&mut self, field: &Field) {
+        if self.threads == 1 {
+            for replica in &mut self.replicas {
+                replica.step(field, Self::EXCHANGE_INTERVAL);
+            }
+            return;
+        }
+        let chunk = self.replicas.len().div_ceil(self.threads);
+        std::thread::scope(|scope| {
+            for replicas in self.replicas.chunks_mut(chunk) {
+                scope.spawn(move || {
+                    for replica in replicas {
+                        replica.step(field, Self::EXCHANGE_INTERVAL);
+                    }
+                });
+            }
+        });
+    }
+
+    // 隣り合う温度のレプリカの状態を確率的に交換し、一番低温のレプリカには最良解を共有する
+    fn exchange(&mut self, field: &Field) {
+        let start = self.round % 2;
+        for i in (start..self.replicas.len() - 1).step_by(2) {
+            let (lo, hi) = self.replicas.split_at_mut(i + 1);
+            let (a, b) = (&mut lo[i], &mut hi[0]);
+            let ea = field.state_score(&mut a.state) as f64;
+            let eb = field.state_score(&mut b.state) as f64;
+            let p = ((1.0 / a.temp - 1.0 / b.temp) * (ea - eb)).exp();
+            if rand_f64() < p {
+                std::mem::swap(&mut a.state, &mut b.state);
+                self.exchanged += 1;
+            }
+        }
+        for replica in &mut self.replicas {
+            if field.state_score(&mut replica.best) < field.state_score(&mut self.best) {
+                self.best = replica.best.clone();
+            }
+        }
+        let coldest = &mut self.replicas[0];
+        if field.state_score(&mut self.best) < field.state_score(&mut coldest.state) {
+            coldest.state = self.best.clone();
+        }
+    }
+
+    fn accepted(&self) -> usize {
+        self.replicas.iter().map(|r| r.accepted).sum()
+    }
+}
+
+// ジャッジでは1スレッドで直列に動かす
+// local feature ならコア数、THREADS 環境変数で指定もできる
+pub(crate) fn worker_threads() -> usize {
+    if !cfg!(feature = "local") {
+        return 1;
+    }
+    let threads = std::env::var("THREADS").ok().and_then(|v| v.parse().ok());
+    threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+// 最小限の JSON、Plan の保存と読み込み、ログと記録の出力に使う (数は整数だけ)
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(v) => write!(f, "{}", v),
+            Json::Number(v) => write!(f, "{}", v),
+            Json::String(v) => {
+                write!(f, "\"")?;
+                for c in v.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { "," } else { "" }, v)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in members.iter().enumerate() {
+                    write!(f, "{}{}:{}", if i > 0 { "," } else { "" }, Json::String(k.clone()), v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl Json {
+    pub(crate) fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Json, String> {
+        let bytes = text.as_bytes();
+        let mut i = 0;
+        let value = Self::parse_value(bytes, &mut i)?;
+        Self::skip_space(bytes, &mut i);
+        if i < bytes.len() {
+            return Err(format!("unexpected trailing data at {}", i));
+        }
+        Ok(value)
+    }
+
+    fn skip_space(bytes: &[u8], i: &mut usize) {
+        while *i < bytes.len() && bytes[*i].is_ascii_whitespace() {
+            *i += 1;
+        }
+    }
+
+    // bytes[*i] が c なら読み飛ばす
+    fn eat(bytes: &[u8], i: &mut usize, c: u8) -> bool {
+        Self::skip_space(bytes, i);
+        if bytes.get(*i) == Some(&c) {
+            *i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(bytes: &[u8], i: &mut usize, c: u8) -> Result<(), String> {
+        if Self::eat(bytes, i, c) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", c as char, i))
+        }
+    }
+
+    fn parse_value(bytes: &[u8], i: &mut usize) -> Result<Json, String> {
+        Self::skip_space(bytes, i);
+        match bytes.get(*i) {
+            Some(b'[') => {
+                *i += 1;
+                let mut values = vec![];
+                if !Self::eat(bytes, i, b']') {
+                    loop {
+                        values.push(Self::parse_value(bytes, i)?);
+                        if Self::eat(bytes, i, b']') {
+                            break;
+                        }
+                        Self::expect(bytes, i, b',')?;
+                    }
+                }
+                Ok(Json::Array(values))
+            }
+            Some(b'{') => {
+                *i += 1;
+                let mut members = vec![];
+                if !Self::eat(bytes, i, b'}') {
+                    loop {
+                        Self::skip_space(bytes, i);
+                        let key = match Self::parse_value(bytes, i)? {
+                            Json::String(key) => key,
+                            _ => return Err(format!("expected key at {}", i)),
+                        };
+                        Self::expect(bytes, i, b':')?;
+                        members.push((key, Self::parse_value(bytes, i)?));
+                        if Self::eat(bytes, i, b'}') {
+                            break;
+                        }
+                        Self::expect(bytes, i, b',')?;
+                    }
+                }
+                Ok(Json::Object(members))
+            }
+            Some(b'n') if bytes[*i..].starts_with(b"null") => {
+                *i += 4;
+                Ok(Json::Null)
+            }
+            Some(b't') if bytes[*i..].starts_with(b"true") => {
+                *i += 4;
+                Ok(Json::Bool(true))
+            }
+            Some(b'f') if bytes[*i..].starts_with(b"false") => {
+                *i += 5;
+                Ok(Json::Bool(false))
+            }
+            // 読むのは Plan だけで、エスケープは使わないので扱わない
+            Some(b'"') => {
+                let start = *i + 1;
+                let len = bytes[start..].iter().position(|&b| b == b'"').ok_or("unterminated string")?;
+                *i = start + len + 1;
+                Ok(Json::String(String::from_utf8_lossy(&bytes[start..start + len]).into_owned()))
+            }
+            Some(&b) if b == b'-' || b.is_ascii_digit() => {
+                let start = *i;
+                *i += 1;
+                while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+                    *i += 1;
+                }
+                let token = std::str::from_utf8(&bytes[start..*i]).unwrap();
+                token.parse().map(Json::Number).map_err(|_| format!("invalid number at {}", start))
+            }
+            _ => Err(format!("unexpected character at {}", i)),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v).ok_or(format!("missing {}", key)),
+            _ => Err(format!("expected object for {}", key)),
+        }
+    }
+
+    fn as_i64(&self) -> Result<i64, String> {
+        match *self {
+            Json::Number(v) => Ok(v),
+            _ => Err("expected number".to_string()),
+        }
+    }
+
+    fn as_array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => Err("expected array".to_string()),
+        }
+    }
+
+    // [a, b] の組
+    fn as_pair(&self) -> Result<(usize, usize), String> {
+        match self.as_array()? {
+            [a, b] => Ok((a.as_i64()? as usize, b.as_i64()? as usize)),
+            _ => Err("expected pair".to_string()),
+        }
+    }
+}
+
+// 掘る予定の木、JSON で保存して読み込める (木を見比べたり、手で直したり、長く回した結果を初期解にしたり)
+// keys と edges は sampling の index、cells は edges の最短路で壊すマス (見る用で、読み込むときは使わない)
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Plan {
+    pub(crate) n: usize,
+    pub(crate) score: i32,
+    pub(crate) sampling: Vec<Pos>,
+    pub(crate) keys: Vec<usize>,
+    pub(crate) edges: Vec<(usize, usize)>,
+    pub(crate) cells: Vec<Pos>,
+}
+
+impl Plan {
+    pub(crate) fn to_json(&self) -> String {
+        let join = |items: Vec<String>| format!("[{}]", items.join(", "));
+        let pos = |v: &[Pos]| join(v.iter().map(|p| format!("[{}, {}]", p.y, p.x)).collect());
+        let mut res = String::from("{\n");
+        res += &format!("  \"n\": {},\n", self.n);
+        res += &format!("  \"score\": {},\n", self.score);
+        res += &format!("  \"sampling\": {},\n", pos(&self.sampling));
+        res += &format!("  \"keys\": {},\n", join(self.keys.iter().map(|k| k.to_string()).collect()));
+        res += &format!("  \"edges\": {},\n", join(self.edges.iter().map(|(s, t)| format!("[{}, {}]", s, t)).collect()));
+        res += &format!("  \"cells\": {}\n", pos(&self.cells));
+        res += "}\n";
+        res
+    }
+
+    pub(crate) fn from_json(text: &str) -> Result<Plan, SolverError> {
+        let parse = || -> Result<Plan, String> {
+            let json = Json::parse(text)?;
+            let pos = |key: &str| -> Result<Vec<Pos>, String> {
+                json.get(key)?.as_array()?.iter().map(|v| v.as_pair().map(|(y, x)| Pos::new(y, x))).collect()
+            };
+            let sampling = pos("sampling")?;
+            let keys = json.get("keys")?.as_array()?.iter().map(|v| v.as_i64().map(|k| k as usize)).collect::<Result<Vec<_>, _>>()?;
+            let edges = json.get("edges")?.as_array()?.iter().map(|v| v.as_pair()).collect::<Result<Vec<_>, _>>()?;
+            if let Some(&i) = keys.iter().chain(edges.iter().flat_map(|(s, t)| [s, t])).find(|&&i| i >= sampling.len()) {
+                return Err(format!("index {} is out of sampling", i));
+            }
+            Ok(Plan {
+                n: json.get("n")?.as_i64()? as usize,
+                score: json.get("score")?.as_i64()? as i32,
+                sampling,
+                keys,
+                edges,
+                cells: pos("cells")?,
+            })
+        };
+        parse().map_err(SolverError::InvalidPlan)
+    }
+}
+
+pub(crate) struct State {
+    keys: Vec<usize>,
+    edges: Vec<(usize, usize)>,
+    score: Option<i32>,
+}
+
+impl State {
+    fn new(keys: &[usize], edges: &[(usize, usize)]) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            edges: edges.to_vec(),
+            score: None,
+        }
+    }
+
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            edges: self.edges.clone(),
+            score: None,
+        }
+    }
+
+    fn check(&self, sources: &[usize], houses: &[usize], n: usize) -> bool {
+        let mut uf = UnionFind::new(n);
+        for &(s, t) in &self.edges {
+            uf.merge(s, t);
+        }
+        houses.iter().all(|&h| sources.iter().any(|&s| uf.same(h, s)))
+    } 
+
+}
+
+struct Solver {
+    sources: Vec<Pos>,
+    houses: Vec<Pos>,
+    field: Field,
+    oracle: Oracle,
+    warm_start: Option<Plan>, // 探索の初期解にする木
+}
+
+impl Solver {
+    fn new<J: Judge>(judge: &mut J, oracle: Oracle, strategy: Option<Strategy>, warm_start: Option<Plan>) -> Result<Self, SolverError> {
+        let Input { n, w, k, c, sources, houses } = judge.read_input()?;
+        let strategy = strategy.unwrap_or_else(|| local_strategy(Strategy::select(w, k, c)));
+        log(Level::Info, "strategy", format_args!("{}", strategy));
+        Ok(Self {
+            sources, houses, field: Field::new(n, w, k, c, strategy), oracle, warm_start,
+        })
+    }
+
+    fn solve<J: Judge>(&mut self, judge: &mut J, budget: &mut Budget) -> Result<(), SolverError> {
+        // field init
+        budget.begin(Phase::Sampling);
+        if self.oracle == Oracle::None {
+            self.field.guess_field(&self.sources, &self.houses, judge)?;
+            budget.begin(Phase::Estimation);
+            self.field.guess_estimate();
+        } else {
+            self.field.guess_oracle(&self.sources, &self.houses, judge, self.oracle)?;
+        }
+        budget.begin(Phase::AllPairs);
+        self.field.build_paths(worker_threads());
+        budget.now_time(("finish guess_field").to_string());
+
+        // init state
+        budget.begin(Phase::Search);
+        let mut init_state = match &self.warm_start {
+            Some(plan) => self.field.state_from_plan(plan),
+            None => self.field.generate_init_state(),
+        };
+        budget.now_time(format!("finish generate init_state, warm start: {}, score: {}", self.warm_start.is_some(), self.field.state_score(&mut init_state)));
+
+        // claiming
+        let mut current_state = match self.field.strategy.search {
+            Search::Greedy => init_state,
+            Search::Tempering => {
+                let mut tempering = Tempering::new(&self.field, &mut init_state, worker_threads());
+                tempering.run(&self.field, budget);
+                budget.now_time(format!("round: {}, accept: {}, exchange: {}, threads: {}", tempering.round, tempering.accepted(), tempering.exchanged, tempering.threads));
+                budget.now_time(format!("moves: {}", tempering.replicas[0].moves.summary()));
+                tempering.best
+            }
+        };
+
+        self.field.plan = Some(self.field.plan(&mut current_state));
+
+        self.field.guess_output(&self.sources, &self.houses);
+
+        // output
+        budget.begin(Phase::Excavation);
+        self.field.done(&current_state, judge)
+    }
+}
+
+
+pub(crate) fn run<J: Judge>(judge: &mut J, budget: &mut Budget) -> Result<Field, SolverError> {
+    run_with(judge, budget, Oracle::None, None, None)
+}
+
+// strategy が None なら Strategy::TABLE から選ぶ、warm_start があれば探索をその木から始める
+pub(crate) fn run_with<J: Judge>(judge: &mut J, budget: &mut Budget, oracle: Oracle, strategy: Option<Strategy>, warm_start: Option<Plan>) -> Result<Field, SolverError> {
+    let mut solver = Solver::new(judge, oracle, strategy, warm_start)?;
+    let res = solver.solve(judge, budget);
+    let field = &solver.field;
+    field.cost.report();
+    let completed = res.is_ok();
+    record(
+        "score",
+        vec![
+            ("w", Json::Number(field.w as i64)),
+            ("k", Json::Number(field.k as i64)),
+            ("c", Json::Number(field.c as i64)),
+            ("cost", Json::Number(field.cost.total() as i64)),
+            ("completed", Json::Bool(completed)),
+            ("oracle", Json::String(format!("{:?}", oracle))),
+            ("strategy", Json::String(field.strategy.to_string())),
+            ("breakdown", field.cost.to_json()),
+        ],
+    );
+    res.map(|_| solver.field)
+}
+
+// local feature なら環境変数で再現できる実行にできる
+// SEED=<種> で乱数を固定し、CLAIMS=<回数> で Search を claim の回数で打ち切る (数でなければ較正値)
+fn local_budget() -> Budget {
+    if !cfg!(feature = "local") {
+        return Budget::new();
+    }
+    if let Some(seed) = std::env::var("SEED").ok().and_then(|v| v.parse().ok()) {
+        seed_rng(seed);
+    }
+    match std::env::var("CLAIMS") {
+        Ok(v) => {
+            let claims = v.parse().unwrap_or_else(|_| Budget::calibrated_claims(Budget::TIME_LIMIT));
+            Budget::with_claims(Budget::TIME_LIMIT, claims)
+        }
+        Err(_) => Budget::new(),
+    }
+}
+
+// STRATEGY=sampling=Lattice20,search=Greedy で表から選んだ戦略の一部を差し替える
+fn local_strategy(strategy: Strategy) -> Strategy {
+    if !cfg!(feature = "local") {
+        return strategy;
+    }
+    let spec = std::env::var("STRATEGY").unwrap_or_default();
+    strategy.with_overrides(&spec).unwrap_or_else(|e| {
+        log(Level::Error, "strategy", format_args!("STRATEGY: {}", e));
+        strategy
+    })
+}
+
+// PLAN_IN=<path> に保存した木から探索を始める (入力ファイルを読むときだけ)
+fn local_warm_start() -> Result<Option<Plan>, SolverError> {
+    match std::env::var("PLAN_IN") {
+        Ok(path) => {
+            let text = std::fs::read_to_string(&path).map_err(|e| SolverError::InvalidPlan(format!("{}: {}", path, e)))?;
+            Plan::from_json(&text).map(Some)
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+// ORACLE=plan|exact で本当の耐久値を使う (入力ファイルを読むときだけ)
+fn local_oracle() -> Oracle {
+    match std::env::var("ORACLE").as_deref() {
+        Ok("plan") => Oracle::Plan,
+        Ok("exact") => Oracle::Exact,
+        _ => Oracle::None,
+    }
+}
+
+// LOG=info,search=debug LOG_SINK=comment|stderr|file:<path> LOG_FORMAT=text|json でログの出し方を変える
+fn local_logger() {
+    if !cfg!(feature = "local") {
+        return;
+    }
+    let var = |key: &str| std::env::var(key).unwrap_or_default();
+    if let Err(e) = Logger::configure(&var("LOG"), &var("LOG_SINK"), &var("LOG_FORMAT")) {
+        log(Level::Error, "main", format_args!("logger: {}", e));
+    }
+}
+
+fn main() {
+    local_logger();
+    let mut budget = local_budget();
+    let args = std::env::args().collect::<Vec<_>>();
+    let res = if cfg!(feature = "local") && args.len() >= 2 {
+        // 手元では入力ファイルを直接読んで、本当の耐久値を知っているジャッジで動かす
+        // main <input> [waste]
+        let oracle = local_oracle();
+        let file = std::fs::File::open(&args[1]).map_err(|e| SolverError::Input(e.to_string()));
+        let judge = file.and_then(|f| LocalJudge::from_reader(std::io::BufReader::new(f)));
+        match judge.and_then(|judge| Ok((judge, local_warm_start()?))) {
+            Ok((mut judge, warm_start)) => {
+                let res = run_with(&mut judge, &mut budget, oracle, None, warm_start);
+                if let (Ok(field), Some(path)) = (&res, args.get(2)) {
+                    if let Err(e) = field.waste_output(&judge, path) {
+                        log(Level::Error, "main", format_args!("cannot write {}: {}", path, e));
+                    }
+                }
+                // PLAN_OUT=<path> に掘った木を保存する
+                if let (Ok(Field { plan: Some(plan), .. }), Ok(path)) = (&res, std::env::var("PLAN_OUT")) {
+                    if let Err(e) = std::fs::write(&path, plan.to_json()) {
+                        log(Level::Error, "main", format_args!("cannot write {}: {}", path, e));
+                    }
                 }
-                steps.push((y, x));
+                // ジャッジ側で数えたコスト、score の記録の cost と一致するはず
+                log(Level::Info, "cost", format_args!("judge total: {}", judge.total_cost));
+                res
             }
+            Err(e) => Err(e),
         }
-        // べつに、サンプリングしていない点でもそれを使ってごにょごにょしていいじゃん！
-        // ただ、これやったところで誤差レベル...？
-        for &(y, x) in &steps {
-            let min_dist = checks.iter().map(|&(cy, cx)| (cy as i32 - y as i32).abs() + (cx as i32 - x as i32).abs()).min().unwrap();
-            if min_dist <= arrowed_min_dist {
-                continue;
-            }
-            // 一番近いhouses, sourcesが規定値以上離れてるならサボる
-            let near_house_dist = houses.iter().map(|&(cy, cx)| (cy as i32 - y as i32).abs() + (cx as i32 - x as i32).abs()).min().unwrap();
-            let near_source_dist = sources.iter().map(|&(cy, cx)| (cy as i32 - y as i32).abs() + (cx as i32 - x as i32).abs()).min().unwrap();
-            if near_house_dist >= rejected_min_dist && near_source_dist >= rejected_min_dist {
-                checks.push((y, x));
-                self.guess[y][x] = 4500;
-                continue;
+    } else {
+        let stdin = std::io::stdin();
+        let mut judge = IoJudge::new(stdin.lock(), std::io::stdout());
+        run(&mut judge, &mut budget)
+    };
+    budget.report();
+    if let Err(e) = res {
+        log(Level::Error, "main", format_args!("{}", e));
+        std::process::exit(1);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CS: [usize; 8] = [1, 2, 4, 8, 16, 32, 64, 128];
+
+    // ジャッジと同じく、累積powerが耐久値以上になるまで叩いてコストを数える
+    fn simulate(model: &CostModel, durability: i32, start: usize) -> i32 {
+        let mut rest = durability;
+        let mut cost = 0;
+        for power in model.powers(start) {
+            cost += model.c + power;
+            rest -= power;
+            if rest <= 0 {
+                break;
             }
-            self.guess[y][x] = self.destruct(y, x, true, &vec![], line_source);
-            checks.push((y, x));
         }
+        assert!(rest <= 0);
+        cost
+    }
 
-        for y in 0..self.n {
-            for x in 0..self.n {
-                if checks.iter().any(|&(cy, cx)| cy == y && cx == x) {
-                    continue;
+    #[test]
+    fn cost_model_matches_simulated_judge() {
+        for &c in &CS {
+            let model = CostModel::new(c, Strategy::select(1, 1, c).schedule);
+            for durability in (10..=5000).step_by(7) {
+                for start in 0..model.schedule.len() - 1 {
+                    assert_eq!(model.hit_cost(durability, start), simulate(&model, durability, start), "c: {}, durability: {}, start: {}", c, durability, start);
                 }
-                // 一番近いchecksの値を採用
-                let &(ny, nx) = checks.iter().min_by_key(|&&(cy, cx)| (cy as i32 - y as i32).abs() + (cx as i32 - x as i32).abs()).unwrap();
-                self.guess[y][x] = self.guess[ny][nx];
             }
         }
-        // for _ in 0..40 {
-        // for _ in 0..30 {
-        // for _ in 0..20 {
-        for _ in 0..15 {
-            self.guess_flatten();
+    }
+
+    #[test]
+    fn cost_model_powers_are_valid() {
+        for &c in &CS {
+            let model = CostModel::new(c, Strategy::select(1, 1, c).schedule);
+            for start in 0..model.schedule.len() - 1 {
+                assert!(model.powers(start).all(|p| (1..=5000).contains(&p)));
+            }
         }
+    }
 
-        // sampling の各点から各点へのdist, ... を求めておく
-        for &s in &self.sampling {
-            self.dist_path.push(self.dijkstra_vec(s, &self.sampling));
+    // C が大きいほど不確かなマスを避ける、sigma が 0 なら残りの耐久値 + C
+    #[test]
+    fn linear_cost_risk_grows_with_c() {
+        let mut last = 0.0;
+        for &c in &CS {
+            let model = CostModel::new(c, Schedule::Backoff);
+            assert!(model.risk_lambda >= last, "c: {}", c);
+            last = model.risk_lambda;
+            assert_eq!(model.linear_cost(300, 0), 300 + c as i32);
+            assert_eq!(model.linear_cost(300, 1000), 300 + c as i32 + (model.risk_lambda * 1000.0) as i32);
         }
+        assert_eq!(CostModel::new(1, Schedule::Backoff).risk_lambda, 0.0);
+        assert!(CostModel::new(128, Schedule::Backoff).risk_lambda > 0.0);
+    }
 
-        // 頂点集合idとそれぞれの距離のみ見ながら、それらのpathを(s, t) のみ管理してufでmerge管理...すればいいかんじ？
-        // 焼きなましで高々115個の頂点のみを見ればよいのでうれしい
+    #[test]
+    fn expected_cost_without_sigma_is_hit_cost() {
+        for &c in &CS {
+            let model = CostModel::new(c, Strategy::select(1, 1, c).schedule);
+            for mu in (10..=5000).step_by(13) {
+                let expected = model.expected_cost(mu, 0);
+                let (a, b) = (model.hit_cost(mu, model.start_index(mu, true)), model.hit_cost(mu, model.start_index(mu, false)));
+                let want = if c < 64 { a as f64 } else { (a + b) as f64 / 2.0 };
+                assert!((expected - want).abs() < 1e-6, "c: {}, mu: {}", c, mu);
+            }
+        }
     }
 
-    fn guess_flatten(&mut self) {
-        let mut guess = vec![vec![0; self.n]; self.n];
-        let dxy = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
-        for y in 0..self.n {
-            for x in 0..self.n {
-                let mut sum = 0;
-                let mut cnt = 0;
-                for &(dy, dx) in &dxy {
-                    if let Some((ny, nx)) = convert_index(y, dy, x, dx, self.n) {
-                        sum += self.guess[ny][nx];
-                        cnt += 1;
-                    }
+    // 入力としてありうる (W, K, C) はどれも表のどこかの行に当てはまる
+    #[test]
+    fn strategy_table_covers_all_cases() {
+        let within = |v: usize, (l, r): Bounds| l <= v && v <= r;
+        for w in 1..=4 {
+            for k in 1..=10 {
+                for &c in &CS {
+                    assert!(Strategy::TABLE.iter().any(|&(rw, rk, rc, _)| within(w, rw) && within(k, rk) && within(c, rc)), "w: {}, k: {}, c: {}", w, k, c);
                 }
-                guess[y][x] = sum / cnt as i32;
             }
         }
-        self.guess = guess;
     }
 
-    // guess_field をerrで出力
-    fn guess_output(&self, sources: &Vec<(usize, usize)>, houses: &Vec<(usize, usize)>) {
-        eprintln!("{} {} {} {}", self.n, self.w, self.k, self.c);
-        for y in 0..self.n {
-            eprintln!("{}", self.guess[y].iter().map(|&x| x.to_string()).collect::<Vec<_>>().join(" "));
-        }
-        for &(y, x) in sources {
-            eprintln!("{} {} ", y, x);
-        }
-        for &(y, x) in houses {
-            eprintln!("{} {} ", y, x);
+    #[test]
+    fn strategy_overrides_by_name() {
+        let base = Strategy::select(1, 1, 1);
+        assert_eq!(base.with_overrides(""), Ok(base));
+        let s = base.with_overrides("sampling=Lattice20,search=Greedy").unwrap();
+        assert_eq!((s.sampling, s.estimator, s.cost, s.schedule, s.search), (SamplingPlan::Lattice20, base.estimator, base.cost, base.schedule, Search::Greedy));
+        assert_eq!(base.with_overrides("cost=Expected").unwrap().cost, PathCost::Expected);
+        for &e in &Estimator::ALL {
+            assert_eq!(base.with_overrides(&format!("estimator={:?}", e)).unwrap().estimator, e);
         }
+        assert!(base.with_overrides("search=Anneal").is_err());
+        assert!(base.with_overrides("step=12").is_err());
+        assert!(base.with_overrides("Greedy").is_err());
     }
 
-    fn dijkstra(&self, s: (usize, usize), t: (usize, usize)) -> (i32, Vec<(usize, usize)>) {
-        let (sy, sx) = s;
-        let (ty, tx) = t;
-        let mut dist = vec![vec![std::i32::MAX; self.n]; self.n];
-        let mut que = std::collections::BinaryHeap::new();
-        que.push(std::cmp::Reverse((0, (sy, sx))));
-        dist[sy][sx] = 0;
-        let dyx = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
-        let cost = |y: usize, x: usize| {
-            self.guess[y][x]
-        };
-        while let Some(std::cmp::Reverse((d, (y, x)))) = que.pop() {
-            if y == ty && x == tx {
-                break;
-            }
-            if d > dist[y][x] {
-                continue;
-            }
-            for &(dy, dx) in &dyx {
-                if let Some((ny, nx)) = convert_index(y, dy, x, dx, self.n) {
-                    let c = cost(ny, nx);
-                    if dist[ny][nx] <= d + c {
-                        continue;
-                    }
-                    dist[ny][nx] = d + c;
-                    que.push(std::cmp::Reverse((d + c, (ny, nx))));
-                }
-            }
+    #[test]
+    fn plan_json_round_trip() {
+        seed_rng(9);
+        let field = random_field(30, 20, 2, 4);
+        let mut keys = (0..6).collect::<Vec<_>>();
+        keys.extend((6..20).filter(|_| rand(0, 2) == 0));
+        let plan = field.plan(&mut field.state_generate(&keys));
+        assert!(plan.edges.iter().all(|&(s, t)| plan.cells.contains(&field.sampling[s]) && plan.cells.contains(&field.sampling[t])));
+        assert_eq!(Plan::from_json(&plan.to_json()).unwrap(), plan);
+    }
+
+    #[test]
+    fn plan_json_rejects_malformed() {
+        let valid = r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 1], "edges": [[0, 1]], "cells": []}"#;
+        assert!(Plan::from_json(valid).is_ok());
+        for text in [
+            "",
+            r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 1], "edges": [[0, 1]]}"#,
+            r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 2], "edges": [[0, 1]], "cells": []}"#,
+            r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 1], "edges": [[0, 1, 1]], "cells": []}"#,
+            r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 1], "edges": [[0, 1]], "cells": [],}"#,
+            r#"{"n": 3, "score": 5, "sampling": [[0, 0], [2, 2]], "keys": [0, 1], "edges": [[0, 1]], "cells": []} x"#,
+        ] {
+            assert!(matches!(Plan::from_json(text), Err(SolverError::InvalidPlan(_))), "{}", text);
         }
-        // 復元
-        let mut res = vec![(ty, tx)];
-        loop {
-            let &(y, x) = res.last().unwrap();
-            if dist[y][x] == 0 {
-                break;
-            }
-            if y == sy && x == sx {
-                break;
-            } 
-            // println!("# dist: {}, pos: {}, {}, cost: {}", dist[y][x], y, x, cost(y, x));
-            for &(dy, dx) in &dyx {
-                if let Some((py, px)) = convert_index(y, dy, x, dx, self.n) {
-                    if dist[y][x] == dist[py][px] + cost(y, x) {
-                        res.push((py, px));
-                        break;
+    }
+
+    // sampling の並びが違う plan でも、座標で対応させて同じ木になる
+    #[test]
+    fn state_from_plan_maps_by_position() {
+        seed_rng(10);
+        let field = random_field(30, 20, 2, 4);
+        let mut keys = (0..6).collect::<Vec<_>>();
+        keys.extend((6..20).filter(|_| rand(0, 2) == 0));
+        let mut plan = field.plan(&mut field.state_generate(&keys));
+        let m = plan.sampling.len();
+        plan.sampling.reverse();
+        plan.keys.iter_mut().for_each(|k| *k = m - 1 - *k);
+        plan.edges.iter_mut().for_each(|(s, t)| (*s, *t) = (m - 1 - *s, m - 1 - *t));
+        let mut state = field.state_from_plan(&plan);
+        assert_eq!(field.plan(&mut state).cells, plan.cells);
+        assert_eq!(field.state_score(&mut state), plan.score);
+
+        // 辺が足りなければ keys から作り直す
+        plan.edges.clear();
+        let mut state = field.state_from_plan(&plan);
+        assert!(state.check(&field.sources_idx, &field.houses_idx, m));
+        assert_eq!(field.state_score(&mut state), field.state_score(&mut field.state_generate(&keys)));
+    }
+
+    #[test]
+    fn logger_filters_by_level_and_target() {
+        let logger = Logger { level: Level::Info, targets: vec![("guess".to_string(), Level::Debug), ("cost".to_string(), Level::Off)], sink: Sink::Stderr, json: false, stdout_closed: false };
+        assert!(logger.enabled(Level::Error, "budget"));
+        assert!(logger.enabled(Level::Info, "budget"));
+        assert!(!logger.enabled(Level::Debug, "budget"));
+        assert!(logger.enabled(Level::Debug, "guess"));
+        assert!(!logger.enabled(Level::Error, "cost"));
+        assert!(!logger.enabled(Level::Off, "budget"));
+        assert!(Logger::configure("verbose", "", "").is_err());
+        assert!(Logger::configure("", "stdout", "").is_err());
+        assert!(Logger::configure("", "", "yaml").is_err());
+    }
+
+    #[test]
+    fn logger_formats_lines() {
+        let mut logger = Logger { level: Level::Info, targets: vec![], sink: Sink::Comment, json: false, stdout_closed: false };
+        assert_eq!(logger.format(Level::Info, "cost", "total: 1\nnext"), "# [info] cost: total: 1\n# next");
+        logger.sink = Sink::Stderr;
+        assert_eq!(logger.format(Level::Warn, "cost", "total: 1"), "[warn] cost: total: 1");
+        logger.json = true;
+        let line = logger.format(Level::Debug, "guess", "a \"b\"");
+        assert_eq!(line, r#"{"level":"debug","target":"guess","message":"a \"b\""}"#);
+    }
+
+    // Completed の後はジャッジが標準出力を読まないので、ログと記録を標準エラーに回す
+    #[test]
+    fn io_judge_closes_stdout_on_completed() {
+        let mut out = vec![];
+        let mut judge = IoJudge::new("0 2".as_bytes(), &mut out);
+        assert!(matches!(judge.query(Pos::new(1, 2), 100), Ok(Responce::NotBroken)));
+        assert!(!LOGGER.lock().unwrap().stdout_closed);
+        assert!(matches!(judge.query(Pos::new(3, 4), 100), Ok(Responce::Completed)));
+        assert!(LOGGER.lock().unwrap().stdout_closed);
+        assert_eq!(String::from_utf8(out).unwrap(), "1 2 100\n3 4 100\n");
+    }
+
+    #[test]
+    fn json_display_round_trip() {
+        let json = Json::object(vec![("a", Json::Array(vec![Json::Number(-3), Json::Null, Json::Bool(true)])), ("b", Json::String("x y".to_string()))]);
+        assert_eq!(Json::parse(&json.to_string()), Ok(json));
+        assert_eq!(Json::String("\"\\\n\t".to_string()).to_string(), r#""\"\\\n\u0009""#);
+    }
+
+    // 素朴に連結成分のラベルを持つ UnionFind
+    #[test]
+    fn union_find_matches_naive_partition() {
+        seed_rng(1);
+        for _ in 0..200 {
+            let n = rand(1, 30);
+            let mut uf = UnionFind::new(n);
+            let mut label = (0..n).collect::<Vec<_>>();
+            for _ in 0..rand(0, 2 * n) {
+                let (a, b) = (rand(0, n), rand(0, n));
+                let leader = uf.merge(a, b);
+                assert_eq!(uf.leader(a), leader);
+                assert_eq!(uf.leader(b), leader);
+                let (la, lb) = (label[a], label[b]);
+                label.iter_mut().filter(|l| **l == lb).for_each(|l| *l = la);
+                for i in 0..n {
+                    for j in 0..n {
+                        assert_eq!(uf.same(i, j), label[i] == label[j]);
                     }
                 }
             }
         }
-        return (dist[ty][tx], res);
     }
 
-    // TODO: (Vec<i32>, Vec<Vec<(usize, usize)>>) を返すように
-    fn dijkstra_vec(&self, s: (usize, usize), v: &Vec<(usize, usize)>) -> Vec<(i32, Vec<(usize, usize)>)> {
-        let (sy, sx) = s;
-        let mut dist = vec![vec![std::i32::MAX; self.n]; self.n];
-        let mut que = std::collections::BinaryHeap::new();
-        que.push(std::cmp::Reverse((0, (sy, sx))));
-        dist[sy][sx] = 0;
-        let cost = |y: usize, x: usize| {
-            // self.guess[y][x]
-            std::cmp::max(1, self.guess[y][x] - self.real[y][x]) + self.c as i32
-        };
-        while let Some(std::cmp::Reverse((d, (y, x)))) = que.pop() {
-            if d > dist[y][x] {
-                continue;
-            }
-            for &(dy, dx) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                if let Some((ny, nx)) = convert_index(y, dy, x, dx, self.n) {
-                    let c = cost(ny, nx);
-                    if dist[ny][nx] <= d + c {
-                        continue;
-                    }
-                    dist[ny][nx] = d + c;
-                    que.push(std::cmp::Reverse((d + c, (ny, nx))));
-                }
-            }
+    #[test]
+    fn union_find_merge_same_set_is_noop() {
+        let mut uf = UnionFind::new(3);
+        uf.merge(0, 1);
+        let leader = uf.merge(1, 0);
+        assert_eq!(uf.merge(0, 0), leader);
+        assert!(uf.same(0, 1));
+        assert!(!uf.same(0, 2));
+    }
+
+    // 2x2 を壊すと4マス目で、すでにつながっている2マスとマージする
+    #[test]
+    fn local_judge_breaks_square_block() {
+        let input = Input { n: 4, w: 1, k: 1, c: 1, sources: vec![Pos::new(3, 0)], houses: vec![Pos::new(3, 3)] };
+        let mut judge = LocalJudge::new(input, Grid::new(4, 10));
+        for p in [Pos::new(0, 0), Pos::new(0, 1), Pos::new(1, 1), Pos::new(1, 0)] {
+            assert!(matches!(judge.query(p, 10), Ok(Responce::Broken)));
         }
+        let index = |p: Pos| judge.is_broken.flat_index(p);
+        let (a, b) = (index(Pos::new(0, 0)), index(Pos::new(1, 1)));
+        assert!(judge.uf.same(a, b));
+        assert!(!judge.completed);
+        assert_eq!(judge.total_cost, 4 * 11);
+    }
 
-        let mut res = vec![];
-        for &(ty, tx) in v {
-            let mut path = vec![(ty, tx)];
-            loop {
-                let &(y, x) = path.last().unwrap();
-                if dist[y][x] == 0 {
-                    break;
-                }
-                if y == sy && x == sx {
-                    break;
-                } 
-                for &(dy, dx) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                    if let Some((py, px)) = convert_index(y, dy, x, dx, self.n) {
-                        if dist[y][x] == dist[py][px] + cost(y, x) {
-                            path.push((py, px));
-                            break;
+    #[test]
+    fn pos_neighbors_stay_in_grid() {
+        for n in 1..5 {
+            for y in 0..n {
+                for x in 0..n {
+                    let p = Pos::new(y, x);
+                    let mut want = vec![];
+                    for (dir, &(dy, dx)) in DYX.iter().enumerate() {
+                        let (ny, nx) = (y as i32 + dy, x as i32 + dx);
+                        let inside = 0 <= ny && ny < n as i32 && 0 <= nx && nx < n as i32;
+                        let q = if inside { Some(Pos::new(ny as usize, nx as usize)) } else { None };
+                        assert_eq!(p.step(dir as u8, n), q, "n: {}, p: {:?}", n, p);
+                        if let Some(q) = q {
+                            assert_eq!(q.back(dir as u8, n), Some(p));
+                            assert_eq!(p.manhattan(q), 1);
+                            want.push((dir as u8, q));
                         }
                     }
+                    assert_eq!(p.neighbors(n).collect::<Vec<_>>(), want);
                 }
             }
-            res.push((dist[ty][tx], path));
         }
-        res
+        assert_eq!(Pos::new(0, 0).step(0, 200), None);
+        assert_eq!(Pos::new(199, 199).step(1, 200), None);
+        assert_eq!(Pos::new(199, 199).step(3, 200), None);
+        assert_eq!(Pos::new(0, 0).step(2, 200), None);
+        assert_eq!(Pos::new(199, 0).step(0, 200), Some(Pos::new(198, 0)));
+        assert_eq!(Pos::new(3, 10).manhattan(Pos::new(7, 2)), 12);
     }
 
-    fn query<R: BufRead>(&mut self, y: usize, x: usize, power: i32, line_source: &mut LineSource<R>) -> Responce {
-        if self.is_broken[y][x] {
-            return Responce::Broken;
+    #[test]
+    fn grid_is_row_major() {
+        let grid = Grid::from_fn(3, |p| p.y * 10 + p.x);
+        assert_eq!(grid.positions().map(|p| grid[p]).collect::<Vec<_>>(), vec![0, 1, 2, 10, 11, 12, 20, 21, 22]);
+        assert_eq!(grid.rows().map(|row| row.to_vec()).collect::<Vec<_>>(), vec![vec![0, 1, 2], vec![10, 11, 12], vec![20, 21, 22]]);
+        for p in grid.positions() {
+            assert_eq!(grid.pos(grid.flat_index(p)), p);
+            assert_eq!(grid[grid.flat_index(p)], grid[p]);
         }
-        self.real[y][x] += power;
-        self.total_cost += self.c + power as usize;
-        println!("{} {} {}", y, x, power);
-        input! {
-            from line_source,
-            res: usize,
-        }
-        match res {
-            0 => Responce::NotBroken,
-            1 => {
-                self.is_broken[y][x] = true;
-                Responce::Broken
-            },
-            2 => {
-                std::process::exit(0);
-            },
-            _ => {
-                println!("# Error: Invalid responce.");
-                std::process::exit(1);
-            },
+        assert_eq!(grid.map(|&v| v + 1)[Pos::new(2, 1)], 22);
+        assert_eq!(Grid::from_vec(3, (0..9).collect()), Grid::from_fn(3, |p| p.y * 3 + p.x));
+    }
+
+    // 窓の中を素朴に足して平均する箱型平均を passes 回
+    fn naive_smoothing(grid: &Grid<i32>, radius: usize, passes: usize, pinned: &Grid<bool>) -> Grid<i32> {
+        let mut cur = grid.map(|&v| v as f64);
+        for _ in 0..passes {
+            cur = Grid::from_fn(grid.n(), |p| {
+                if pinned[p] {
+                    return grid[p] as f64;
+                }
+                let window = cur.positions().filter(|q| q.y.abs_diff(p.y) <= radius && q.x.abs_diff(p.x) <= radius).collect::<Vec<_>>();
+                window.iter().map(|&q| cur[q]).sum::<f64>() / window.len() as f64
+            });
         }
+        cur.map(|&v| v.round() as i32)
     }
 
-    // guess == false ならhousesは不要、&vec![]でよい
-    fn destruct<R: BufRead>(&mut self, y: usize, x: usize, guess: bool, houses: &Vec<(usize, usize)>, line_source: &mut LineSource<R>) -> i32 {
-        if self.is_broken[y][x] {
-            return self.real[y][x];
+    #[test]
+    fn smoothing_matches_naive_box_blur() {
+        seed_rng(8);
+        for _ in 0..100 {
+            let n = rand(1, 12);
+            let grid = Grid::from_fn(n, |_| rand(10, 5001) as i32);
+            let pinned = Grid::from_fn(n, |_| rand(0, 4) == 0);
+            let (radius, passes) = (rand(0, 5), rand(0, 4));
+            let got = Smoothing { radius, passes }.apply(&grid, Some(&pinned));
+            assert_eq!(got, naive_smoothing(&grid, radius, passes, &pinned), "radius: {}, passes: {}", radius, passes);
+            for p in grid.positions().filter(|&p| pinned[p]) {
+                assert_eq!(got[p], grid[p]);
+            }
         }
+    }
 
-        let v = match self.c {
-              1 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1300, 1500, 1750, 2000, 2270, 2500, 2875, 3000, 3350, 3700, 4100, 4500, 5000],
-              2 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1300, 1500, 1750, 2000, 2270, 2500, 2875, 3000, 3350, 3700, 4100, 4500, 5000],
-              4 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1400, 1750, 2270, 2875, 3550, 4200, 5000],
-              8 => vec![0, 15, 25, 40, 65, 95, 140, 190, 250, 330, 415, 520, 650, 840, 1075, 1400, 1750, 2270, 2875, 3550, 4200, 5000],
-             16 => vec![0, 20, 40, 70, 120, 190, 280, 395, 540, 760, 1080, 1515, 2160, 3000, 4000, 5000],
-             32 => vec![0, 20, 40, 70, 120, 190, 280, 395, 540, 760, 1080, 1515, 2160, 3000, 4000, 5000],
-             64 => vec![0, 30, 90, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
-            // 128 => vec![0, 30, 90, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
-            128 => vec![0, 50, 120, 220, 410, 730, 1170, 1700, 2200, 2700, 3500, 4000, 5000],
-              _ => vec![0, 25, 60, 120, 210, 350, 570, 960, 1600, 2800, 5000],
-        };
-        if guess {
-            // house なら破壊する
-            let lim = if houses.iter().any(|&(ty, tx)| ty == y && tx == x) {
-                5000
-            } else {
-                500
-            };
+    #[test]
+    fn smoothing_rounds_and_keeps_constants() {
+        let constant = Grid::new(5, 1234);
+        assert_eq!(Smoothing { radius: 2, passes: 3 }.apply(&constant, None), constant);
+        // 平均 0.75 は切り捨てずに 1 にする
+        let grid = Grid::from_vec(2, vec![0, 1, 1, 1]);
+        assert_eq!(Smoothing { radius: 1, passes: 1 }.apply(&grid, None), Grid::new(2, 1));
+        // 4近傍平均 15 回 (1軸あたりの分散 7.5) は幅 5 の箱型平均 3 回くらい
+        assert_eq!(Smoothing::gaussian(2.74, 3), Smoothing { radius: 2, passes: 3 });
+        assert_eq!(Smoothing::gaussian(0.0, 3), Smoothing { radius: 0, passes: 3 });
+    }
 
-            // 最後サボる
-            for i in 0..v.len() - 1 {
-                if v[i + 1] >= lim {
-                    break;
-                }
-                self.query(y, x, v[i + 1] - v[i], line_source);
+    // n×n の盤面、plan_cost は [1, 100)、2割くらいを破壊済(コスト0)にして、sampling 点を points 個置く
+    // sampling の先頭 w 個を水源、続く k 個を家とする
+    fn random_field(n: usize, points: usize, w: usize, k: usize) -> Field {
+        let mut field = Field::new(n, w, k, 1, Strategy::select(w, k, 1));
+        field.plan_cost = Grid::from_fn(n, |_| rand(1, 100) as i32);
+        field.is_broken = Grid::from_fn(n, |_| rand(0, 5) == 0);
+        while field.sampling.len() < points {
+            let p = Pos::new(rand(0, n), rand(0, n));
+            if !field.sampling.contains(&p) {
+                field.sampling.push(p);
             }
-            if self.is_broken[y][x] {
-                return self.real[y][x];
-            } 
-            return 4500
-        } 
-
-        // v を2倍にする
-        let mut v2 = vec![];
-        let mut u = 1;
-        for &e in &v {
-            if let Some(&last) = v2.last() {
-                u -= 1;
-                if u < 0 {
-                    v2.push((last + e) / 2);
-                }
-            } 
-            v2.push(e);
         }
-        let v = v2;
-
-        // 隣接マスにrealが有効なものがある -> その値を叩く   
+        field.sources_idx = (0..w).collect();
+        field.houses_idx = (w..w + k).collect();
+        field.build_paths(1);
+        field
+    }
 
-        let dxy = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
-        let mut i = 0;
-        for &(dy, dx) in &(dxy) {
-            if let Some((ny, nx)) = convert_index(y, dy, x, dx, self.n) {
-                if !self.is_broken[ny][nx] {
+    // 入ったマスのコストを辺の重みとして、全辺を緩和し続ける
+    fn bellman_ford(field: &Field, s: Pos) -> Grid<i32> {
+        let mut dist = Grid::new(field.n, i32::MAX);
+        dist[s] = 0;
+        loop {
+            let mut updated = false;
+            for p in dist.positions() {
+                if dist[p] == i32::MAX {
                     continue;
                 }
-                // self.real[ny][nx] を越える最大のv[i]を探す
-                while i < v.len() - 1 && v[i + 1] <= self.real[ny][nx] {
-                    i += 1;
-                }
-                if i > 1 {
-                    if self.c < 64 {
-                        i = (i as i32 - 1) as usize;
-                    } else {
-                        if rand(0, 2) == 1 {
-                            i = (i as i32 - 1) as usize;
-                        }
+                for (_, q) in p.neighbors(field.n) {
+                    let nd = dist[p] + field.cell_cost(q);
+                    if nd < dist[q] {
+                        dist[q] = nd;
+                        updated = true;
                     }
                 }
-
-                self.query(y, x, v[i], line_source);
-                break;
-            } 
-        }
-        for i in i..v.len() - 1 {
-            self.query(y, x, v[i + 1] - v[i], line_source);
+            }
+            if !updated {
+                return dist;
+            }
         }
-        return self.real[y][x]
     }
 
-    fn generate_init_state(&self) -> State {
-        let mut keys = vec![];
-        for &h in &self.houses_idx {
-            keys.push(h);
-        }
-        for &s in &self.sources_idx {
-            keys.push(s);
+    // path が t から s への4近傍の列で、s 以外のマスのコストの和が dist になっているか
+    fn check_path(field: &Field, s: Pos, t: Pos, path: &[Pos], dist: i32) {
+        assert_eq!(path.first(), Some(&t));
+        assert_eq!(path.last(), Some(&s));
+        for w in path.windows(2) {
+            assert_eq!(w[0].manhattan(w[1]), 1, "{:?} -> {:?}", w[0], w[1]);
         }
-        let mut res = self.state_generate(&keys);
+        let cost = path[..path.len() - 1].iter().map(|&p| field.cell_cost(p)).sum::<i32>();
+        assert_eq!(cost, dist, "s: {:?}, t: {:?}", s, t);
+    }
 
-        for add in 0..self.sampling.len() {
-            if keys.iter().any(|&x| x == add) {
-                continue;
-            } 
-            let mut new_keys = keys.clone();
-            new_keys.push(add);
-            let mut new_res = self.state_generate(&keys);
-            if self.state_score(&mut new_res) < self.state_score(&mut res) {
-                res = new_res;
+    #[test]
+    fn dijkstra_vec_matches_bellman_ford() {
+        seed_rng(2);
+        for _ in 0..100 {
+            let n = rand(1, 9);
+            let field = random_field(n, rand(1, n * n + 1), 1, 0);
+            for (si, &s) in field.sampling.iter().enumerate() {
+                let want = bellman_ford(&field, s);
+                let (dist, _) = field.dijkstra_vec(s, &field.sampling);
+                let (radix, _) = field.dijkstra_vec_with::<RadixHeap>(s, &field.sampling);
+                for (ti, &t) in field.sampling.iter().enumerate() {
+                    assert_eq!(dist[ti], want[t]);
+                    assert_eq!(radix[ti], want[t]);
+                    assert_eq!(field.dist(si, ti), want[t]);
+                    check_path(&field, s, t, &field.path(si, ti), field.dist(si, ti));
+                }
             }
         }
-        res
     }
 
-    fn done<R: BufRead>(&mut self, state: &State, line_source: &mut LineSource<R>) {
-        println!("# done start");
-        if !state.check(&self.sources_idx, &self.houses_idx, self.sampling.len()) {
-            println!("# invalid state");
-            panic!("invalid state");
-        }
-        let mut break_pos = vec![];
-        for &(s, t) in &state.edges {
-            let (_, path) = &self.dist_path[s][t];
-            for &(y, x) in path {
-                break_pos.push((y, x));
+    // スレッド数によらず同じ順で同じ結果になる
+    #[test]
+    fn all_pairs_is_independent_of_threads() {
+        seed_rng(7);
+        for _ in 0..20 {
+            let n = rand(1, 12);
+            let field = random_field(n, rand(1, n * n + 1), 1, 0);
+            let serial = field.all_pairs(1);
+            for threads in [2, 3, 8, 1000] {
+                let parallel = field.all_pairs(threads);
+                assert_eq!(parallel.len(), serial.len());
+                for ((d1, p1), (d2, p2)) in serial.iter().zip(&parallel) {
+                    assert_eq!(d1, d2, "threads: {}", threads);
+                    assert_eq!(p1, p2, "threads: {}", threads);
+                }
             }
         }
-        for &(y, x) in &break_pos {
-            self.destruct(y, x, false, &vec![], line_source);
-        }
     }
 
-    fn state_score(&self, state: &mut State) -> i32 {
-        if let Some(v) = state.score {
-            return v
-        }
-        let mut res = 0;
-        for &(s, t) in &state.edges {
-            let (dist, _) = self.dist_path[s][t];
-            res += dist;
+    #[test]
+    fn point_to_point_search_matches_bellman_ford() {
+        seed_rng(3);
+        for _ in 0..100 {
+            let n = rand(1, 9);
+            let field = random_field(n, 1, 1, 0);
+            let mut buf = SearchBuffer::new(n);
+            let mut radix = SearchBuffer::<RadixHeap>::new(n);
+            buf.prepare(&field);
+            for _ in 0..10 {
+                let s = Pos::new(rand(0, n), rand(0, n));
+                let t = Pos::new(rand(0, n), rand(0, n));
+                let want = bellman_ford(&field, s)[t];
+                let (dist, path) = field.astar(s, t, &mut buf);
+                assert_eq!(dist, want, "astar");
+                check_path(&field, s, t, &path, dist);
+                let (dist, path) = field.bidirectional(s, t, &mut buf);
+                assert_eq!(dist, want, "bidirectional");
+                check_path(&field, s, t, &path, dist);
+                let (dist, path) = field.bidirectional(s, t, &mut radix);
+                assert_eq!(dist, want, "bidirectional radix");
+                check_path(&field, s, t, &path, dist);
+            }
         }
-        state.score = Some(res);
-        res
     }
 
-    fn claim(&self, state: &State) -> State {
-        // 確率で色々する
-        let mut keys = state.keys.clone();
-        if rand(0, 2) == 0 {
-            self.state_erase_key(&mut keys);
-        }
-        let cnt = rand(1, 3);
-        for _ in 0..cnt {
-            self.state_add_key(&mut keys);
+    // 取り出した距離以上の値だけを push しながら、二分ヒープと同じ順に出てくるか
+    #[test]
+    fn radix_heap_matches_binary_queue() {
+        seed_rng(6);
+        for _ in 0..200 {
+            let (mut radix, mut binary) = (RadixHeap::default(), BinaryQueue::default());
+            let mut last = 0;
+            for step in 0..rand(1, 300) {
+                if rand(0, 3) > 0 {
+                    let d = last + rand(0, 1 << rand(0, 20)) as i32;
+                    radix.push(d, step);
+                    binary.push(d, step);
+                }
+                assert_eq!(radix.peek(), binary.peek());
+                let (a, b) = (radix.pop(), binary.pop());
+                // 同じ距離の中の順番は問わない
+                assert_eq!(a.map(|(d, _)| d), b.map(|(d, _)| d));
+                if let Some((d, _)) = a {
+                    last = d;
+                }
+            }
+            radix.clear();
+            assert_eq!(radix.pop(), None);
         }
-        // let cnt = rand(1, 3);
-        // for _ in 0..cnt {
-        //     match rand(0, 2) {
-        //         1 => self.state_add_key(&mut keys),
-        //         _ => self.state_erase_key(&mut keys),
-        //     }
-        // }
-        self.state_generate(&keys)
     }
 
-    fn state_add_key(&self, keys: &mut Vec<usize>) {
-        // 0..self.sampling.len() で、state.keysに入っていない値をstate.keysに追加
-        let add = rand(0, self.sampling.len());
-        if keys.iter().all(|&x| x != add) {
-            keys.push(add);
-        }
+    #[test]
+    fn state_check_rejects_disconnected_trees() {
+        let (sources, houses) = ([0, 3], [1, 2]);
+        assert!(State::new(&[0, 1, 2], &[(0, 1), (1, 2)]).check(&sources, &houses, 5));
+        assert!(State::new(&[0, 1, 2, 3], &[(0, 1), (3, 2)]).check(&sources, &houses, 5));
+        assert!(State::new(&[0, 1, 2, 4], &[(4, 1), (2, 4), (4, 0)]).check(&sources, &houses, 5));
+        assert!(!State::new(&[0, 1, 2], &[(0, 1)]).check(&sources, &houses, 5));
+        assert!(!State::new(&[0, 1, 2, 4], &[(0, 1), (2, 4)]).check(&sources, &houses, 5));
+        assert!(!State::new(&[1, 2], &[(1, 2)]).check(&sources, &houses, 5));
+        assert!(!State::new(&[], &[]).check(&sources, &houses, 5));
     }
 
-    fn state_erase_key(&self, keys: &mut Vec<usize>) {
-        let del = rand(0, keys.len());
-        if self.houses_idx.iter().all(|&x| x != del) && self.sources_idx.iter().all(|&x| x != del) {
-            keys.remove(del);
+    // 辺集合を隣接リストにして、家から水源に辿り着けるかを素朴に調べる
+    #[test]
+    fn state_check_matches_naive_search() {
+        seed_rng(4);
+        for _ in 0..500 {
+            let n = rand(2, 10);
+            let w = rand(1, n);
+            let sources = (0..w).collect::<Vec<_>>();
+            let houses = (w..n).filter(|_| rand(0, 2) == 0).collect::<Vec<_>>();
+            let edges = (0..rand(0, n)).map(|_| (rand(0, n), rand(0, n))).collect::<Vec<_>>();
+            let reachable = |h: usize| {
+                let mut seen = vec![false; n];
+                let mut stack = vec![h];
+                seen[h] = true;
+                while let Some(v) = stack.pop() {
+                    for &(a, b) in &edges {
+                        for (u, to) in [(a, b), (b, a)] {
+                            if u == v && !seen[to] {
+                                seen[to] = true;
+                                stack.push(to);
+                            }
+                        }
+                    }
+                }
+                sources.iter().any(|&s| seen[s])
+            };
+            let want = houses.iter().all(|&h| reachable(h));
+            assert_eq!(State::new(&[], &edges).check(&sources, &houses, n), want, "edges: {:?}", edges);
         }
     }
 
-    fn state_generate(&self, keys: &Vec<usize>) -> State {
-        let mut edges = vec![];
-        let mut uf = UnionFind::new(self.sampling.len());
-
-        let mut kruskal_edges = vec![];
-
-        for &s in keys {
-            for &t in keys {
-                let (dist, _) = self.dist_path[s][t];
-                kruskal_edges.push((dist, s, t));
+    #[test]
+    fn state_generate_yields_valid_forest() {
+        seed_rng(5);
+        for _ in 0..100 {
+            let n = rand(4, 12);
+            let (w, k) = (rand(1, 4), rand(1, 6));
+            let field = random_field(n, w + k + rand(0, 10), w, k);
+            let m = field.sampling.len();
+            let mut keys = (0..w + k).collect::<Vec<_>>();
+            keys.extend((w + k..m).filter(|_| rand(0, 2) == 0));
+            let state = field.state_generate(&keys);
+
+            assert!(state.check(&field.sources_idx, &field.houses_idx, m));
+            // 端末は全部残っていて、辺の端点は keys に含まれる
+            assert!((0..w + k).all(|v| state.keys.contains(&v)));
+            assert!(state.edges.iter().all(|&(s, t)| state.keys.contains(&s) && state.keys.contains(&t)));
+            // 閉路がない
+            let mut uf = UnionFind::new(m);
+            for &(s, t) in &state.edges {
+                assert!(!uf.same(s, t), "cycle: {:?}", state.edges);
+                uf.merge(s, t);
             }
-        }
-        kruskal_edges.sort_by(|a, b| a.0.cmp(&b.0));
-
-        for &(_, s, t) in &kruskal_edges {
-            if uf.same(s, t) {
-                continue;
+            // 水源同士はつながない
+            for (i, &a) in field.sources_idx.iter().enumerate() {
+                for &b in &field.sources_idx[i + 1..] {
+                    assert!(!uf.same(a, b));
+                }
             }
-            let s_is_ok = self.sources_idx.iter().any(|&x| uf.same(x, s));
-            let t_is_ok = self.sources_idx.iter().any(|&x| uf.same(x, t));
-            if s_is_ok && t_is_ok {
-                continue;
+            // Steiner 点は葉にならない
+            for &v in &state.keys {
+                let degree = state.edges.iter().filter(|&&(s, t)| s == v || t == v).count();
+                assert!(field.is_terminal(v) || degree >= 2, "leaf: {}", v);
             }
-            uf.merge(s, t);
-            edges.push((s, t));
         }
-        State::new(&keys, &edges)
     }
 
-}
-
-struct State {
-    keys: Vec<usize>,
-    edges: Vec<(usize, usize)>,
-    score: Option<i32>,
-}
-
-impl State {
-    fn new(keys: &Vec<usize>, edges: &Vec<(usize, usize)>) -> Self {
-        Self {
-            keys: keys.clone(),
-            edges: edges.clone(),
-            score: None,
+    // 提出するビルドでは並列にしない
+    #[test]
+    fn judge_build_is_single_threaded() {
+        if !cfg!(feature = "local") {
+            assert_eq!(worker_threads(), 1);
         }
     }
 
-    fn clone(&self) -> Self {
-        Self {
-            keys: self.keys.clone(),
-            edges: self.edges.clone(),
-            score: None,
+    // レプリカごとに乱数を持つので、スレッド数によらず同じ探索になる
+    #[test]
+    fn tempering_is_independent_of_threads() {
+        seed_rng(8);
+        let (w, k) = (2, 4);
+        let field = random_field(12, w + k + 20, w, k);
+        let keys = (0..field.sampling.len()).collect::<Vec<_>>();
+        let run = |threads: usize| {
+            seed_rng(9);
+            let mut tempering = Tempering::new(&field, &mut field.state_generate(&keys), threads);
+            for round in 1..=5 {
+                tempering.round = round;
+                tempering.step(&field);
+                tempering.exchange(&field);
+            }
+            tempering.replicas.iter().map(|r| (r.state.keys.clone(), r.state.edges.clone(), r.accepted)).collect::<Vec<_>>()
+        };
+        let serial = run(1);
+        for threads in [2, 3, 8] {
+            assert_eq!(run(threads), serial, "threads: {}", threads);
         }
     }
 
-    fn check(&self, sources: &Vec<usize>, houses: &Vec<usize>, n: usize) -> bool {
-        let mut uf = UnionFind::new(n);
-        for &(s, t) in &self.edges {
-            uf.merge(s, t);
-        }
-        houses.iter().all(|&h| sources.iter().any(|&s| uf.same(h, s)))
-    } 
-
-}
-
-struct Solver {
-    n: usize,
-    w: usize,
-    k: usize,
-    c: usize,
-    sources: Vec<(usize, usize)>,
-    houses: Vec<(usize, usize)>,
-    field: Field,
-}
-
-impl Solver {
-    fn new<R: BufRead>(line_source: &mut LineSource<R>) -> Self {
-        input! {
-            from line_source,
-            n: usize,
-            w: usize,
-            k: usize,
-            c: usize,
-            sources: [(usize, usize); w],
-            houses: [(usize, usize); k],
-        }
-        Self {
-            n, w, k, c, sources, houses, field: Field::new(n, w, k, c),
+    // 位置ではなく格子点の番号で端末かどうかを見る (keys の先頭が端末とは限らない)
+    #[test]
+    fn state_erase_key_keeps_terminals() {
+        seed_rng(6);
+        for _ in 0..100 {
+            let n = rand(4, 12);
+            let (w, k) = (rand(1, 4), rand(1, 6));
+            let field = random_field(n, w + k + rand(1, 10), w, k);
+            let mut keys = (0..field.sampling.len()).rev().collect::<Vec<_>>();
+            // Steiner 点が無くなるまで消し続ける
+            while keys.len() > w + k {
+                field.state_erase_key(&mut keys);
+                assert!((0..w + k).all(|v| keys.contains(&v)), "keys: {:?}", keys);
+            }
         }
     }
 
-    fn solve<R: BufRead>(&mut self, line_source: &mut LineSource<R>, timer: &Timer) {
-        // field init
-        self.field.guess_field(&self.sources, &self.houses, line_source);
-        timer.now_time(("finish guess_field").to_string());
-        // ここまでで3.5secつかってるけど、testerの方で吸われていそう
-
-        // init state
-        let mut init_state = self.field.generate_init_state();
-        timer.now_time(("finish generate init_state").to_string());
-
-        // let mut current_state = init_state.clone();
-        let mut current_states = vec![];
+    // Steiner 点が無い状態では Swap は使えないので Random として返す
+    #[test]
+    fn claim_reports_fallback_move() {
+        seed_rng(7);
         for _ in 0..20 {
-            current_states.push(init_state.clone());
-        }
-
-        let tl = 4.5;
-        // let tl = 10.0;
-
-        let mut cnt = 0;
-        let mut acc = 0;
-        // // claiming
-        while timer.is_timeout(tl) {
-            cnt += 1;
-            let mut next_states = vec![];
-            for state in &mut current_states {
-                let mut next_state = self.field.claim(state);
-                if self.field.state_score(&mut next_state) < self.field.state_score(state) {
-                    next_states.push(next_state.clone());
-                } else {
-                    next_states.push(state.clone());
-                }
-            }
-            current_states = next_states;
-        }
-        
-        let mut current_state = &mut init_state;
-        for state in &mut current_states {
-            if self.field.state_score(current_state) > self.field.state_score(state) {
-                current_state = state;
-            }
+            let n = rand(4, 12);
+            let (w, k) = (rand(1, 4), rand(1, 6));
+            let field = random_field(n, w + k + rand(1, 10), w, k);
+            let state = field.state_generate(&(0..w + k).collect::<Vec<_>>());
+            let (_, mv) = field.claim(&state, Move::Swap);
+            assert_eq!(mv, Move::Random);
+            let (_, mv) = field.claim(&state, Move::Random);
+            assert_eq!(mv, Move::Random);
         }
-        
-        timer.now_time(format!("count: {}, accept: {}", cnt, acc));
-
-        // eprintln!
-        self.field.guess_output(&self.sources, &self.houses);
-
-        // output
-        self.field.done(current_state, line_source);
-
-
     }
 }
-
-
-fn main() {
-    let timer = Timer::new();
-    let stdin = std::io::stdin();
-    let mut line_source = LineSource::new(BufReader::new(stdin.lock()));
-    let mut solver = Solver::new(&mut line_source);
-    solver.solve(&mut line_source, &timer);
-}
\ No newline at end of file
@@ -0,0 +1,43 @@
+// ベンチマーク用の合成ケース、提出する main.rs には入れない
+// #[path = "../synthetic.rs"] mod synthetic; で solver と一緒に読み込む
+
+use crate::solver::{Grid, Input, LocalJudge, Pos};
+
+// ガウス関数の山と谷を重ねた耐久値 (10..=5000) の盤面
+// rand の実装が変わってもケースが変わらないように自前の xorshift を使う
+pub(crate) fn generate(seed: u64, w: usize, k: usize, c: usize) -> LocalJudge {
+    const N: usize = 200;
+    const BUMPS: usize = 30;
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut uniform = |l: f64, r: f64| l + (r - l) * (next() >> 11) as f64 / (1u64 << 53) as f64;
+    let bumps = (0..BUMPS)
+        .map(|_| (uniform(0.0, N as f64), uniform(0.0, N as f64), uniform(10.0, 50.0), uniform(-1.0, 1.0)))
+        .collect::<Vec<_>>();
+    let height = Grid::from_fn(N, |p| {
+        bumps
+            .iter()
+            .map(|&(by, bx, s, a)| {
+                let d2 = (p.y as f64 - by).powi(2) + (p.x as f64 - bx).powi(2);
+                a * (-d2 / (2.0 * s * s)).exp()
+            })
+            .sum::<f64>()
+    });
+    let lo = height.positions().map(|p| height[p]).fold(f64::MAX, f64::min);
+    let hi = height.positions().map(|p| height[p]).fold(f64::MIN, f64::max);
+    let durability = height.map(|&h| (10.0 + 4990.0 * ((h - lo) / (hi - lo)).powi(2)).clamp(10.0, 5000.0) as i32);
+    let mut points = vec![];
+    while points.len() < w + k {
+        let p = Pos::new((next() % N as u64) as usize, (next() % N as u64) as usize);
+        if !points.contains(&p) {
+            points.push(p);
+        }
+    }
+    let houses = points.split_off(w);
+    LocalJudge::new(Input { n: N, w, k, c, sources: points, houses }, durability)
+}
@@ -0,0 +1,172 @@
+// tests/in の固定したケースと乱数の種でソルバーを回して、tests/snapshot.txt のスコアと比べる回帰テスト
+// cargo test --test regression
+// スコアを更新するときは UPDATE_SNAPSHOTS=1 cargo test --test regression
+
+#[path = "../src/main.rs"]
+#[allow(dead_code)]
+mod solver;
+
+use solver::{Budget, Estimator, LocalJudge, Oracle, PathCost, Plan, SamplingPlan, Schedule, Search, Strategy};
+
+const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshot.txt");
+// tools/in と同じ形式 (N W K C, 耐久値, 水源, 家) のケース
+const INPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/in");
+// 探索は仮想時計 (claim の回数) で打ち切るので、同じコードなら機械によらず同じスコアになる
+const TOLERANCE: f64 = 0.05; // ケースごと
+const TOTAL_TOLERANCE: f64 = 0.02; // 合計
+// 1ケースあたりの制限時間(秒)、Search はこれに相当する回数だけ claim する
+const TIME_LIMIT: f32 = 1.0;
+const RNG_SEED: u64 = 0;
+
+// (seed, W, K, C): tests/in/{seed:04}.txt の W, K, C、C は全種類、W と K は両端を含むように選んだ
+const CASES: [(u64, usize, usize, usize); 10] = [
+    (1, 1, 1, 1),
+    (2, 4, 10, 2),
+    (3, 1, 10, 4),
+    (4, 4, 1, 8),
+    (5, 2, 5, 16),
+    (6, 4, 10, 32),
+    (7, 1, 10, 64),
+    (8, 4, 1, 128),
+    (9, 1, 1, 128),
+    (10, 3, 7, 1),
+];
+
+fn solve(seed: u64, w: usize, k: usize, c: usize) -> usize {
+    solve_with(seed, w, k, c, Oracle::None, None)
+}
+
+fn load(seed: u64, w: usize, k: usize, c: usize) -> LocalJudge {
+    let path = format!("{}/{:04}.txt", INPUT_DIR, seed);
+    let file = std::fs::File::open(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    let judge = LocalJudge::from_reader(std::io::BufReader::new(file)).unwrap();
+    let input = &judge.input;
+    assert_eq!((input.w, input.k, input.c), (w, k, c), "{}", path);
+    judge
+}
+
+fn solve_with(seed: u64, w: usize, k: usize, c: usize, oracle: Oracle, strategy: Option<Strategy>) -> usize {
+    let mut judge = load(seed, w, k, c);
+    let mut budget = Budget::with_claims(TIME_LIMIT, Budget::calibrated_claims(TIME_LIMIT));
+    solver::seed_rng(RNG_SEED);
+    if let Err(e) = solver::run_with(&mut judge, &mut budget, oracle, strategy, None) {
+        panic!("seed {}: {}", seed, e);
+    }
+    assert!(judge.completed, "seed {}: not connected", seed);
+    judge.total_cost
+}
+
+// "seed W K C cost" の行
+fn read_snapshot() -> Vec<(u64, usize)> {
+    let text = std::fs::read_to_string(SNAPSHOT).expect("tests/snapshot.txt がない (UPDATE_SNAPSHOTS=1 で作る)");
+    text.lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| {
+            let v = line.split_whitespace().collect::<Vec<_>>();
+            (v[0].parse().unwrap(), v[4].parse().unwrap())
+        })
+        .collect()
+}
+
+fn write_snapshot(scores: &[usize]) {
+    let mut text = String::from("# seed W K C cost\n");
+    for (&(seed, w, k, c), score) in CASES.iter().zip(scores) {
+        text += &format!("{} {} {} {} {}\n", seed, w, k, c, score);
+    }
+    std::fs::write(SNAPSHOT, text).unwrap();
+}
+
+#[test]
+fn scores_do_not_regress() {
+    let scores = CASES.iter().map(|&(seed, w, k, c)| solve(seed, w, k, c)).collect::<Vec<_>>();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        write_snapshot(&scores);
+        return;
+    }
+    let snapshot = read_snapshot();
+    let mut failures = vec![];
+    for (&(seed, w, k, c), &score) in CASES.iter().zip(&scores) {
+        let expected = match snapshot.iter().find(|&&(s, _)| s == seed) {
+            Some(&(_, expected)) => expected,
+            None => panic!("seed {} がスナップショットにない (UPDATE_SNAPSHOTS=1 で更新する)", seed),
+        };
+        let ratio = score as f64 / expected as f64;
+        println!("seed: {}, W: {}, K: {}, C: {}, cost: {}, snapshot: {}, ratio: {:.3}", seed, w, k, c, score, expected, ratio);
+        if ratio > 1.0 + TOLERANCE {
+            failures.push(format!("seed {}: {} -> {} ({:+.1}%)", seed, expected, score, (ratio - 1.0) * 100.0));
+        }
+    }
+    let total = scores.iter().sum::<usize>();
+    let expected = snapshot.iter().map(|&(_, s)| s).sum::<usize>();
+    let ratio = total as f64 / expected as f64;
+    println!("total: {}, snapshot: {}, ratio: {:.3}", total, expected, ratio);
+    if ratio > 1.0 + TOTAL_TOLERANCE {
+        failures.push(format!("total: {} -> {} ({:+.1}%)", expected, total, (ratio - 1.0) * 100.0));
+    }
+    assert!(failures.is_empty(), "regressions:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn virtual_clock_is_reproducible() {
+    let (seed, w, k, c) = CASES[1];
+    assert_eq!(solve(seed, w, k, c), solve(seed, w, k, c));
+}
+
+// 本当の耐久値で木を作るとき、1マスを残りの耐久値ちょうどの1回で壊す方が叩き方の schedule で壊すより安い
+// plan が普通に解くより安いことは保証されないので比べない (探索も掘るときの叩き方も乱数で変わる)
+#[test]
+fn exact_oracle_is_cheapest() {
+    for &(seed, w, k, c) in &[CASES[2], CASES[7]] {
+        let plan = solve_with(seed, w, k, c, Oracle::Plan, None);
+        let exact = solve_with(seed, w, k, c, Oracle::Exact, None);
+        assert!(exact <= plan, "seed {}: plan {}, exact {}", seed, plan, exact);
+    }
+}
+
+// 表にない組み合わせでも解けるように、戦略の各部分を1つずつ差し替えて最後までつながるか見る
+// コストは表の戦略の STRATEGY_TOLERANCE 倍まで (今は ±10% くらいに収まっている)
+#[test]
+fn every_strategy_option_connects() {
+    const STRATEGY_TOLERANCE: f64 = 1.25;
+    let (seed, w, k, c) = CASES[4];
+    let base = Strategy::select(w, k, c);
+    let mut strategies = vec![];
+    strategies.extend(SamplingPlan::ALL.iter().map(|&sampling| Strategy { sampling, ..base }));
+    strategies.extend(Estimator::ALL.iter().map(|&estimator| Strategy { estimator, ..base }));
+    strategies.extend(PathCost::ALL.iter().map(|&cost| Strategy { cost, ..base }));
+    strategies.extend(Schedule::ALL.iter().map(|&schedule| Strategy { schedule, ..base }));
+    strategies.extend(Search::ALL.iter().map(|&search| Strategy { search, ..base }));
+    strategies.sort_by_key(|s| s.to_string());
+    strategies.dedup();
+    let limit = solve(seed, w, k, c) as f64 * STRATEGY_TOLERANCE;
+    for strategy in strategies {
+        let mut judge = load(seed, w, k, c);
+        let mut budget = Budget::with_claims(TIME_LIMIT, Budget::calibrated_claims(TIME_LIMIT));
+        solver::seed_rng(RNG_SEED);
+        if let Err(e) = solver::run_with(&mut judge, &mut budget, Oracle::None, Some(strategy), None) {
+            panic!("{}: {}", strategy, e);
+        }
+        assert!(judge.completed, "{}: not connected", strategy);
+        assert!((judge.total_cost as f64) <= limit, "{}: cost {} > {:.0}", strategy, judge.total_cost, limit);
+    }
+}
+
+// 保存した木を JSON から読み込んで探索せずに掘ると、同じマスを掘って同じコストになる
+// (C < 64 なら掘るときに乱数を使わない)
+#[test]
+fn warm_start_reproduces_plan() {
+    let (seed, w, k, c) = CASES[2];
+    let run = |strategy: Option<Strategy>, warm_start: Option<Plan>| {
+        let mut judge = load(seed, w, k, c);
+        let mut budget = Budget::with_claims(TIME_LIMIT, Budget::calibrated_claims(TIME_LIMIT));
+        solver::seed_rng(RNG_SEED);
+        let field = solver::run_with(&mut judge, &mut budget, Oracle::None, strategy, warm_start).unwrap();
+        (judge.total_cost, field.plan.unwrap())
+    };
+    let (cost, plan) = run(None, None);
+    let loaded = Plan::from_json(&plan.to_json()).unwrap();
+    let greedy = Strategy { search: Search::Greedy, ..Strategy::select(w, k, c) };
+    let (warm_cost, warm_plan) = run(Some(greedy), Some(loaded));
+    assert_eq!(warm_plan.cells, plan.cells);
+    assert_eq!(warm_cost, cost);
+}
@@ -1,10 +1,20 @@
 import subprocess
 import pipes
+import json
 import multiprocessing
+import os
+import re
 import sys
 
 CASE = 500
 TL = 100
+# 手元のジャッジ(本当の耐久値が分かる)で動かす、main を --features local でビルドしておく
+LOCAL = '--local' in sys.argv
+# --oracle=plan / --oracle=exact で本当の耐久値を使って解く (--local のときだけ)
+ORACLE = next((a.split('=', 1)[1] for a in sys.argv if a.startswith('--oracle=')), None)
+PURPOSES = ['Terminal', 'Lattice', 'Excavation']
+# seed ごとのスコア (features.py が読む)
+SCORES = 'tools/scores.tsv'
 
 def execute_case(seed):
     input_file_path = f'tools/in/{seed:04}.txt'
@@ -13,12 +23,42 @@ def execute_case(seed):
     with open(input_file_path) as fin:
         with open(output_file_path, 'w') as fout:
             with open(pipe_file_path, 'w') as fpipe:
-                subprocess.run(['tools/target/release/tester', 'main/target/release/main'], stdin=fin, stdout=fout, stderr = fpipe, timeout=TL).stdout
-            output = open(pipe_file_path).read()
-            assert output 
+                if LOCAL:
+                    # マスごとの叩き方を tools/waste に出す (waste.py で可視化)
+                    waste_file_path = f'tools/waste/{seed:04}.txt'
+                    env = dict(os.environ, ORACLE=ORACLE) if ORACLE else None
+                    subprocess.run(['main/target/release/main', input_file_path, waste_file_path], stdout=fout, stderr = fpipe, timeout=TL, env=env)
+                else:
+                    subprocess.run(['tools/target/release/tester', 'main/target/release/main'], stdin=fin, stdout=fout, stderr = fpipe, timeout=TL).stdout
     input_file = open(input_file_path).read()
     _, W, K, C = [int(x) for x in input_file.split()[0 : 4]]
-    return seed, output, W, K, C
+    record = read_score_record(seed)
+    if LOCAL:
+        score = record and record['cost']
+    else:
+        # テスターの数えたコストが正しいスコア、記録は内訳を見るためだけに使う
+        score = read_total_cost(open(pipe_file_path).read())
+    return seed, score, record, W, K, C
+
+# テスターが標準エラーに出す "Total Cost = 123" (main の標準エラーも同じファイルに混ざる)
+def read_total_cost(text):
+    costs = re.findall(r'^Total Cost = (\d+)', text, re.MULTILINE)
+    return int(costs[-1]) if costs else None
+
+# seed の score の記録、ジャッジが終わった後の記録は main が標準エラーに出すのでテスターの標準エラーも見る
+def read_score_record(seed):
+    text = open(f'tools/out/{seed:04}.txt').read() + open(f'tools/memo/pipefile_{seed:04}.txt').read()
+    return read_record(text, 'score')
+
+# main が出す "# record: {JSON}" の行のうち kind が一致するもの
+# score: {"kind": "score", "cost": 123, "completed": true, "breakdown": {"Terminal": {"hits": 1, "overhead": 1, "power": 10, "overshoot": 3 or null}, ...}, ...}
+def read_record(output, kind):
+    for line in output.splitlines():
+        if line.startswith('# record: '):
+            record = json.loads(line[len('# record: '):])
+            if record['kind'] == kind:
+                return record
+    return None
 
 def progress(count):
     sys.stdout.write("\033[2K\033[G")
@@ -29,29 +69,45 @@ def main():
     scores = []
     count = 0
     scores_dict = {}
+    rows = []
+    costs = {p: {'hits': 0, 'overhead': 0, 'power': 0, 'overshoot': 0} for p in PURPOSES}
+    overshoot_known = True
+    # テスターで回したときに score の記録が取れなかったケースがあれば内訳は出さない
+    breakdown_known = True
+    if ORACLE and not LOCAL:
+        print('--oracle needs --local')
+        exit(1)
+    if LOCAL:
+        os.makedirs('tools/waste', exist_ok=True)
     with multiprocessing.Pool(max(1, multiprocessing.cpu_count()-2)) as pool:
-        for seed, output, W, K, C in pool.imap_unordered(execute_case, range(CASE)):
-            try:
-                score = int(output.split()[-1])
-                scores.append((score, f'{seed:04}'))
-                if (W, K, C) not in scores_dict:
-                    scores_dict[(W, K, C)] = (score, 1)
-                else:
-                    (su, cn) = scores_dict[(W, K, C)]
-                    scores_dict[(W, K, C)] = (su + score, cn + 1)
-
-            except ValueError:
-                print(seed, "ValueError", flush = True)
-                print(score, flush = True)
-                exit()
-            except IndexError:
-                print(seed, "IndexError", flush = True)
-                print(score, flush = True)
+        for seed, score, record, W, K, C in pool.imap_unordered(execute_case, range(CASE)):
+            if score is None or (record is not None and not record['completed']):
+                print(seed, "no score" if score is None else "not completed", flush = True)
                 exit()
+            if record is None:
+                breakdown_known = False
+            else:
+                for p, values in record['breakdown'].items():
+                    for key, value in values.items():
+                        if value is None:
+                            overshoot_known = False
+                        else:
+                            costs[p][key] += value
+            scores.append((score, f'{seed:04}'))
+            rows.append((seed, W, K, C, score))
+            if (W, K, C) not in scores_dict:
+                scores_dict[(W, K, C)] = (score, 1)
+            else:
+                (su, cn) = scores_dict[(W, K, C)]
+                scores_dict[(W, K, C)] = (su + score, cn + 1)
             count += 1
             progress(count)
 
     print()
+    with open(SCORES, 'w') as f:
+        f.write('# seed\tW\tK\tC\tscore\n')
+        for row in sorted(rows):
+            f.write('\t'.join(map(str, row)) + '\n')
     scores.sort()
     total = sum([s[0] for s in scores])
     ave = total / CASE
@@ -117,6 +173,14 @@ def main():
     for C, ave in list_C:
         print(f'C: {C:3}, average: {ave}')
     print("--------------------------")
+    if not breakdown_known:
+        print('breakdown: - (some cases have no score record)')
+    else:
+        for p in PURPOSES:
+            c = costs[p]
+            overshoot = c['overshoot'] if overshoot_known else '-'
+            print(f'{p:10}: hits: {c["hits"]}, overhead: {c["overhead"]}, power: {c["power"]}, overshoot: {overshoot}, average: {(c["overhead"] + c["power"]) / CASE}')
+    print("--------------------------")
 
 if __name__ == '__main__':
     main()
\ No newline at end of file
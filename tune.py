@@ -0,0 +1,143 @@
+# 戦略の一部を1つずつ差し替えて python run.py を回し、(W, K, C) のまとまりごとに一番良かった戦略を
+# main/src/main.rs の Strategy::TABLE に書き込む
+# make compile してから python tune.py (書き込まずに表を出すだけなら --dry-run)
+# 結果は tools/tune/*.tsv に残るので、--skip-run でそれを使って表だけ作り直せる
+import os
+import subprocess
+import sys
+
+from run import read_score_record
+
+MAIN = 'main/src/main.rs'
+TUNE_DIR = 'tools/tune'
+SCORES = 'tools/scores.tsv'
+# run.py --local と同じく、手元のジャッジで回す
+LOCAL = '--local' in sys.argv
+BEGIN = '// strategy table begin'
+END = '// strategy table end'
+# '' は表の通り
+CANDIDATES = [
+    '',
+    'sampling=Lattice11',
+    'sampling=Lattice12',
+    'sampling=Lattice20',
+    'estimator=Nearest',
+    'estimator=Smooth',
+    'estimator=Wide',
+    'cost=Linear',
+    'cost=Expected',
+    'schedule=Backoff',
+    'schedule=Mixed',
+    'search=Greedy',
+]
+# まとまりの切り方、C は値ごと
+W_RANGES = [(1, 2), (3, 4)]
+K_RANGES = [(1, 5), (6, 10)]
+CS = [1, 2, 4, 8, 16, 32, 64, 128]
+
+
+def result_path(spec):
+    return f'{TUNE_DIR}/{spec or "table"}.tsv'
+
+
+# score の記録にある、実際に使った戦略
+def read_strategy(seed):
+    record = read_score_record(seed)
+    return record and record['strategy']
+
+
+# STRATEGY=spec で run.py を回して、seed ごとのスコアと実際に使った戦略を残す
+def run(spec):
+    env = dict(os.environ, STRATEGY=spec)
+    subprocess.run([sys.executable, 'run.py'] + (['--local'] if LOCAL else []), env=env, check=True)
+    with open(result_path(spec), 'w') as out:
+        out.write('# seed\tW\tK\tC\tscore\tstrategy\n')
+        for line in open(SCORES):
+            if line.startswith('#'):
+                continue
+            seed, w, k, c, score = line.split()
+            out.write('\t'.join([seed, w, k, c, score, read_strategy(int(seed)) or '-']) + '\n')
+
+
+def read_result(spec):
+    rows = []
+    for line in open(result_path(spec)):
+        if line.startswith('#'):
+            continue
+        seed, w, k, c, score, strategy = line.rstrip('\n').split('\t')
+        rows.append((int(seed), int(w), int(k), int(c), int(score), strategy))
+    return rows
+
+
+def within(v, r):
+    return r[0] <= v <= r[1]
+
+
+# まとまりごとに平均スコアが一番小さい候補の戦略を選ぶ
+def choose(results):
+    table = []
+    for wr in W_RANGES:
+        for kr in K_RANGES:
+            for c in CS:
+                best = None
+                for spec, rows in results.items():
+                    rows = [r for r in rows if within(r[1], wr) and within(r[2], kr) and r[3] == c]
+                    if not rows or any(r[5] == '-' for r in rows):
+                        continue
+                    mean = sum(r[4] for r in rows) / len(rows)
+                    strategies = [r[5] for r in rows]
+                    strategy = max(set(strategies), key=strategies.count)
+                    if best is None or mean < best[0]:
+                        best = (mean, spec, strategy, len(rows))
+                if best is None:
+                    print(f'W: {wr}, K: {kr}, C: {c:3}: no cases')
+                    continue
+                base = [r[4] for r in results.get('', []) if within(r[1], wr) and within(r[2], kr) and r[3] == c]
+                gain = f'{(best[0] / (sum(base) / len(base)) - 1) * 100:+.1f}%' if base else '-'
+                print(f'W: {wr}, K: {kr}, C: {c:3}: {best[1] or "table":20} average: {best[0]:.0f} ({gain}, {best[3]} cases)')
+                table.append((wr, kr, (c, c), best[2]))
+    # C が隣り合っていて戦略が同じ行はまとめる
+    merged = []
+    for wr, kr, cr, strategy in table:
+        if merged and merged[-1][0] == wr and merged[-1][1] == kr and merged[-1][3] == strategy:
+            merged[-1] = (wr, kr, (merged[-1][2][0], cr[1]), strategy)
+        else:
+            merged.append((wr, kr, cr, strategy))
+    return merged
+
+
+def format_table(table):
+    lines = [BEGIN, "const TABLE: &'static [(Bounds, Bounds, Bounds, Strategy)] = &["]
+    for wr, kr, cr, strategy in table:
+        lines.append(f'    (({wr[0]}, {wr[1]}), ({kr[0]}, {kr[1]}), ({cr[0]}, {cr[1]}), {strategy}),')
+    lines += ['];', END]
+    return lines
+
+
+# main.rs の改行コード (CRLF) は変えずに書き戻す
+def write_table(table):
+    src = open(MAIN, newline='').read().split('\n')
+    eol = '\r' if src[0].endswith('\r') else ''
+    begin = next(i for i, line in enumerate(src) if line.strip() == BEGIN)
+    end = next(i for i, line in enumerate(src) if line.strip() == END)
+    indent = src[begin][:len(src[begin]) - len(src[begin].lstrip())]
+    src[begin:end + 1] = [indent + line + eol for line in format_table(table)]
+    open(MAIN, 'w', newline='').write('\n'.join(src))
+
+
+def main():
+    os.makedirs(TUNE_DIR, exist_ok=True)
+    if '--skip-run' not in sys.argv:
+        for spec in CANDIDATES:
+            print(f'STRATEGY={spec}')
+            run(spec)
+    results = {spec: read_result(spec) for spec in CANDIDATES if os.path.exists(result_path(spec))}
+    table = choose(results)
+    print('\n'.join(format_table(table)))
+    if '--dry-run' not in sys.argv:
+        write_table(table)
+        print(f'saved to {MAIN}, make regression で確かめてから UPDATE_SNAPSHOTS=1 で更新する')
+
+
+if __name__ == '__main__':
+    main()
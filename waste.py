@@ -0,0 +1,92 @@
+# python run.py --local で出力した tools/waste/XXXX.txt を可視化する
+# - tools/waste/XXXX.png: 左から 叩きすぎた分(overshoot), 叩いた回数, C * 叩いた回数 のヒートマップ
+# - C ごとの overshoot / 耐久値 のヒストグラムを出力
+import glob
+import struct
+import sys
+import zlib
+
+WASTE_DIR = 'tools/waste'
+SCALE = 2
+GAP = 4
+RATIO_BINS = [0.0, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, float('inf')]
+
+
+def read_waste(path):
+    tokens = open(path).read().split()
+    n, c = int(tokens[0]), int(tokens[1])
+    values = [int(x) for x in tokens[2:]]
+    grid = lambda i: [values[(i * n + y) * n:(i * n + y + 1) * n] for y in range(n)]
+    return n, c, grid(0), grid(1), grid(2)
+
+
+# 0 -> 黒, 1 -> 白 の hot カラーマップ
+def hot(t):
+    t = max(0.0, min(1.0, t))
+    r = min(1.0, t * 3)
+    g = min(1.0, max(0.0, t * 3 - 1))
+    b = min(1.0, max(0.0, t * 3 - 2))
+    return bytes([int(r * 255), int(g * 255), int(b * 255)])
+
+
+def write_png(path, width, height, rows):
+    def chunk(tag, data):
+        body = tag + data
+        return struct.pack('>I', len(data)) + body + struct.pack('>I', zlib.crc32(body) & 0xffffffff)
+    raw = b''.join(b'\x00' + row for row in rows)
+    with open(path, 'wb') as f:
+        f.write(b'\x89PNG\r\n\x1a\n')
+        f.write(chunk(b'IHDR', struct.pack('>IIBBBBB', width, height, 8, 2, 0, 0, 0)))
+        f.write(chunk(b'IDAT', zlib.compress(raw, 9)))
+        f.write(chunk(b'IEND', b''))
+
+
+# 各グリッドを最大値で正規化して(平方根スケール)横に並べる
+def heatmap(path, grids):
+    n = len(grids[0])
+    width = (n * SCALE + GAP) * len(grids) - GAP
+    panels = []
+    for grid in grids:
+        top = max(max(row) for row in grid) or 1
+        panels.append([[hot((v / top) ** 0.5) if v > 0 else b'\x00\x00\x00' for v in row] for row in grid])
+    rows = []
+    for y in range(n):
+        row = b''
+        for i, panel in enumerate(panels):
+            if i > 0:
+                row += b'\x40\x40\x40' * GAP
+            row += b''.join(px * SCALE for px in panel[y])
+        rows.extend([row] * SCALE)
+    write_png(path, width, n * SCALE, rows)
+
+
+def main():
+    paths = sorted(glob.glob(f'{WASTE_DIR}/[0-9][0-9][0-9][0-9].txt'))
+    if not paths:
+        print(f'no waste files in {WASTE_DIR}, run `python run.py --local` first')
+        sys.exit(1)
+
+    ratios = {}
+    for path in paths:
+        n, c, hits, real, durability = read_waste(path)
+        overshoot = [[real[y][x] - durability[y][x] if real[y][x] >= durability[y][x] else 0 for x in range(n)] for y in range(n)]
+        overhead = [[c * hits[y][x] for x in range(n)] for y in range(n)]
+        heatmap(path[:-len('.txt')] + '.png', [overshoot, hits, overhead])
+        for y in range(n):
+            for x in range(n):
+                if hits[y][x] > 0 and real[y][x] >= durability[y][x]:
+                    ratios.setdefault(c, []).append(overshoot[y][x] / durability[y][x])
+
+    print("--------------------------")
+    for c in sorted(ratios):
+        rs = ratios[c]
+        print(f'C: {c:3}, broken: {len(rs)}, average overshoot ratio: {sum(rs) / len(rs):.3f}')
+        for lo, hi in zip(RATIO_BINS, RATIO_BINS[1:]):
+            cnt = sum(1 for r in rs if lo <= r < hi)
+            bar = '#' * round(cnt / len(rs) * 50)
+            print(f'  [{lo:4}, {hi:4}): {cnt:7} {bar}')
+        print("--------------------------")
+
+
+if __name__ == '__main__':
+    main()